@@ -0,0 +1,2 @@
+DROP TABLE SpellbookSpells;
+DROP TABLE Spellbooks;
@@ -0,0 +1,15 @@
+CREATE TABLE Spellbooks (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `guild` BIGINT UNSIGNED NOT NULL ,
+  `owner` BIGINT UNSIGNED NOT NULL ,
+  `name` VARCHAR(100) NOT NULL ,
+  UNIQUE (`guild`, `owner`, `name`)
+);
+
+CREATE TABLE SpellbookSpells (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `spellbook` INT UNSIGNED NOT NULL ,
+  `spell` VARCHAR(255) NOT NULL ,
+  UNIQUE (`spellbook`, `spell`),
+  FOREIGN KEY (`spellbook`) REFERENCES Spellbooks (`id`) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+CREATE TABLE SlotTrackers (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `guild` BIGINT UNSIGNED NOT NULL ,
+  `owner` BIGINT UNSIGNED NOT NULL ,
+  `name` VARCHAR(100) NOT NULL ,
+  `class` VARCHAR(100) NOT NULL ,
+  `level` TINYINT UNSIGNED NOT NULL ,
+  `caster` VARCHAR(32) NULL ,
+  `spent` VARCHAR(64) NOT NULL DEFAULT '0,0,0,0,0,0,0,0,0' ,
+  `pact_spent` TINYINT UNSIGNED NOT NULL DEFAULT 0 ,
+  `arcane_recovery` BOOLEAN NOT NULL DEFAULT FALSE ,
+  UNIQUE (`guild`, `owner`, `name`)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE Concentrations (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `guild` BIGINT UNSIGNED NOT NULL ,
+  `channel` BIGINT UNSIGNED NOT NULL ,
+  `owner` BIGINT UNSIGNED NOT NULL ,
+  `name` VARCHAR(100) NOT NULL ,
+  `spell` VARCHAR(255) NOT NULL ,
+  UNIQUE (`channel`, `owner`, `name`)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE TomeSnapshots (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `guild` BIGINT UNSIGNED NOT NULL ,
+  `source` TEXT NOT NULL ,
+  `hash` BIGINT UNSIGNED NOT NULL ,
+  `created` BIGINT UNSIGNED NOT NULL ,
+  `content` LONGTEXT NOT NULL ,
+  INDEX (`guild`, `source`(255))
+);
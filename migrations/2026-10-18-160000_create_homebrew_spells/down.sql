@@ -0,0 +1,2 @@
+DROP TABLE HomebrewSpellEdits;
+DROP TABLE HomebrewSpells;
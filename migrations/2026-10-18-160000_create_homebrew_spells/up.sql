@@ -0,0 +1,25 @@
+CREATE TABLE HomebrewSpells (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `guild` BIGINT UNSIGNED NOT NULL ,
+  `name` VARCHAR(100) NOT NULL ,
+  `level` TINYINT UNSIGNED NOT NULL ,
+  `school` VARCHAR(20) NOT NULL ,
+  `classes` VARCHAR(255) NOT NULL ,
+  `ritual` BOOLEAN NOT NULL ,
+  `concentration` BOOLEAN NOT NULL ,
+  `casting_time` VARCHAR(100) NOT NULL ,
+  `range` VARCHAR(100) NOT NULL ,
+  `components` VARCHAR(255) NOT NULL ,
+  `duration` VARCHAR(100) NOT NULL ,
+  `description` TEXT NOT NULL ,
+  UNIQUE (`guild`, `name`)
+);
+
+CREATE TABLE HomebrewSpellEdits (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `spell` INT UNSIGNED NOT NULL ,
+  `editor` BIGINT UNSIGNED NOT NULL ,
+  `created` BIGINT UNSIGNED NOT NULL ,
+  `content` TEXT NOT NULL ,
+  FOREIGN KEY (`spell`) REFERENCES HomebrewSpells (`id`) ON DELETE CASCADE
+);
@@ -0,0 +1,8 @@
+CREATE TABLE UploadedTomes (
+  `id` INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  `guild` BIGINT UNSIGNED NOT NULL ,
+  `name` VARCHAR(100) NOT NULL ,
+  `format` VARCHAR(10) NOT NULL ,
+  `content` LONGTEXT NOT NULL ,
+  UNIQUE (`guild`, `name`)
+);
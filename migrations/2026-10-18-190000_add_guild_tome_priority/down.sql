@@ -0,0 +1,3 @@
+ALTER TABLE GuildTomes
+  DROP COLUMN `priority` ,
+  DROP COLUMN `policy`;
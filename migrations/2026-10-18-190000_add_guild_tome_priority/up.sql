@@ -0,0 +1,3 @@
+ALTER TABLE GuildTomes
+  ADD `priority` INT NOT NULL DEFAULT 0 ,
+  ADD `policy` VARCHAR(10) NOT NULL DEFAULT 'merge';
@@ -0,0 +1,10 @@
+CREATE TABLE GuildSettings (
+  `guild` BIGINT UNSIGNED NOT NULL PRIMARY KEY,
+  `edition` VARCHAR(4) NOT NULL DEFAULT '2014' ,
+  `public_lists` BOOLEAN NOT NULL DEFAULT FALSE ,
+  `shared_pages` BOOLEAN NOT NULL DEFAULT TRUE ,
+  `prefix` VARCHAR(16) NULL ,
+  `manager_role` BIGINT UNSIGNED NULL ,
+  `announcement_channel` BIGINT UNSIGNED NULL ,
+  `include_ua` BOOLEAN NOT NULL DEFAULT FALSE
+);
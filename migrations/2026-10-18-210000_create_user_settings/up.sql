@@ -0,0 +1,4 @@
+CREATE TABLE UserSettings (
+  `user` BIGINT UNSIGNED NOT NULL PRIMARY KEY,
+  `page_size` TINYINT UNSIGNED NOT NULL DEFAULT 20
+);
@@ -0,0 +1,8 @@
+CREATE TABLE PaginatedMessages (
+  `id` BIGINT UNSIGNED NOT NULL PRIMARY KEY,
+  `page_size` TINYINT UNSIGNED NOT NULL ,
+  `content` LONGTEXT NOT NULL ,
+  `owner` BIGINT UNSIGNED NULL ,
+  `title` VARCHAR(256) NULL ,
+  `spell_filter` TEXT NULL
+);
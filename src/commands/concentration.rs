@@ -0,0 +1,250 @@
+use diesel::prelude::*;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{self as serenity, CreateEmbed, Mentionable};
+use regex::Regex;
+
+use crate::{models::*, schema::Concentrations, Context, Data, Error};
+
+/// The columns a [`Concentration`] is loaded from.
+const COLUMNS: (
+	Concentrations::id,
+	Concentrations::owner,
+	Concentrations::name,
+	Concentrations::spell,
+) = (
+	Concentrations::id,
+	Concentrations::owner,
+	Concentrations::name,
+	Concentrations::spell,
+);
+
+lazy_static! {
+	static ref DAMAGE_REGEX: Regex =
+		Regex::new(r"(?i)\b(?:take|takes|took|taking)\s+(\d+)\s+(?:\w+\s+)?damage\b").unwrap();
+}
+
+async fn character_name(ctx: Context<'_>, character: Option<String>) -> String {
+	match character {
+		Some(character) => character,
+		None => ctx.author_member().await.map_or_else(
+			|| ctx.author().name.clone(),
+			|member| member.display_name().to_string(),
+		),
+	}
+}
+
+/// Start concentrating on a spell in this channel.
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn concentrate(
+	ctx: Context<'_>,
+	#[description = "Spell to concentrate on"]
+	#[autocomplete = "super::autocomplete_spell"]
+	#[rename = "spell"]
+	spell_name: String,
+	#[description = "Character name (defaults to your display name)"]
+	#[max_length = 100]
+	character: Option<String>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+
+	let found = {
+		let spell_map = ctx.data().spell_map.read().await;
+		spell_map
+			.get(&guild_id)
+			.and_then(|sm| sm.get_spell(&spell_name))
+			.map(|spell| (spell.name.clone(), spell.concentration))
+	};
+	let Some((spell_name, true)) = found else {
+		ctx.say(match found {
+			Some((spell_name, _)) => format!("{spell_name} doesn't require concentration."),
+			None => format!("{spell_name} is not available in this guild."),
+		})
+		.await?;
+		return Ok(());
+	};
+
+	let character = character_name(ctx, character).await;
+	let serenity::GuildId(guild_id) = guild_id;
+	let serenity::ChannelId(channel_id) = ctx.channel_id();
+	let serenity::UserId(owner_id) = ctx.author().id;
+
+	let mut conn = ctx.data().db.lock().await;
+	let previous = Concentrations::table
+		.filter(Concentrations::channel.eq(channel_id))
+		.filter(Concentrations::owner.eq(owner_id))
+		.filter(Concentrations::name.eq(&character))
+		.select(COLUMNS)
+		.first::<Concentration>(&mut *conn)
+		.optional()?;
+
+	conn.transaction(|conn| {
+		diesel::delete(
+			Concentrations::table
+				.filter(Concentrations::channel.eq(channel_id))
+				.filter(Concentrations::owner.eq(owner_id))
+				.filter(Concentrations::name.eq(&character)),
+		)
+		.execute(conn)?;
+
+		diesel::insert_into(Concentrations::table)
+			.values(&NewConcentration {
+				guild: guild_id,
+				channel: channel_id,
+				owner: owner_id,
+				name: &character,
+				spell: &spell_name,
+			})
+			.execute(conn)
+	})?;
+
+	ctx.say(match previous {
+		Some(previous) => format!(
+			"{character} stops concentrating on {} and starts concentrating on {spell_name}.",
+			previous.spell
+		),
+		None => format!("{character} is now concentrating on {spell_name}."),
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// Track concentration in this channel.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands("drop_concentration", "list_concentration")
+)]
+#[allow(clippy::unused_async)]
+pub async fn concentration(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+/// Stop concentrating on a spell.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "drop")]
+async fn drop_concentration(
+	ctx: Context<'_>,
+	#[description = "Character name (defaults to your display name)"]
+	#[max_length = 100]
+	character: Option<String>,
+) -> Result<(), Error> {
+	let character = character_name(ctx, character).await;
+	let serenity::ChannelId(channel_id) = ctx.channel_id();
+	let serenity::UserId(owner_id) = ctx.author().id;
+
+	let mut conn = ctx.data().db.lock().await;
+	let previous = Concentrations::table
+		.filter(Concentrations::channel.eq(channel_id))
+		.filter(Concentrations::owner.eq(owner_id))
+		.filter(Concentrations::name.eq(&character))
+		.select(COLUMNS)
+		.first::<Concentration>(&mut *conn)
+		.optional()?;
+
+	if let Some(previous) = previous {
+		diesel::delete(Concentrations::table.find(previous.id)).execute(&mut *conn)?;
+		ctx.say(format!(
+			"{character} stops concentrating on {}.",
+			previous.spell
+		))
+		.await?;
+	} else {
+		ctx.say(format!(
+			"{character} isn't concentrating on anything in this channel."
+		))
+		.await?;
+	}
+
+	Ok(())
+}
+
+/// List everyone concentrating on a spell in this channel.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+async fn list_concentration(ctx: Context<'_>) -> Result<(), Error> {
+	let serenity::ChannelId(channel_id) = ctx.channel_id();
+
+	let mut conn = ctx.data().db.lock().await;
+	let list = Concentrations::table
+		.filter(Concentrations::channel.eq(channel_id))
+		.order(Concentrations::id)
+		.select(COLUMNS)
+		.load::<Concentration>(&mut *conn)?;
+
+	if list.is_empty() {
+		ctx.say("Nobody is concentrating in this channel.").await?;
+		return Ok(());
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed.title("Concentration").description(
+		list.iter()
+			.map(|c| {
+				format!(
+					"**{}** ({}): {}",
+					c.name,
+					serenity::UserId(c.owner).mention(),
+					c.spell
+				)
+			})
+			.join("\n"),
+	);
+
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// Reminds players concentrating in this channel to make a Constitution save when they post
+/// damage taken, e.g. "I take 12 fire damage".
+pub async fn on_message(
+	ctx: &serenity::Context,
+	message: &serenity::Message,
+	data: &Data,
+) -> Result<(), Error> {
+	if message.author.bot || message.guild_id.is_none() {
+		return Ok(());
+	}
+
+	let Some(damage) = DAMAGE_REGEX
+		.captures(&message.content)
+		.and_then(|captures| captures[1].parse::<u32>().ok())
+	else {
+		return Ok(());
+	};
+
+	let serenity::ChannelId(channel_id) = message.channel_id;
+	let serenity::UserId(owner_id) = message.author.id;
+
+	let list = {
+		let mut conn = data.db.lock().await;
+		Concentrations::table
+			.filter(Concentrations::channel.eq(channel_id))
+			.filter(Concentrations::owner.eq(owner_id))
+			.select(COLUMNS)
+			.load::<Concentration>(&mut *conn)?
+	};
+	if list.is_empty() {
+		return Ok(());
+	}
+
+	let dc = (damage / 2).max(10);
+	let concentrating = list
+		.iter()
+		.map(|c| format!("{} ({})", c.name, c.spell))
+		.join(", ");
+
+	message
+		.reply(
+			ctx,
+			format!("Concentrating: {concentrating}. Make a DC {dc} Constitution saving throw!"),
+		)
+		.await?;
+
+	Ok(())
+}
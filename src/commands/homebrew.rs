@@ -0,0 +1,493 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::prelude::*;
+use futures::{Stream, StreamExt};
+use itertools::Itertools;
+use poise::{
+	serenity_prelude::{self as serenity, CreateEmbed},
+	Modal,
+};
+
+use super::{is_manager, spells::build_spell_map};
+use crate::{
+	data::{Spell, SpellCollection, SpellSchool, SpellSource},
+	models::*,
+	schema::{HomebrewSpellEdits, HomebrewSpells},
+	ApplicationContext, Context, Error,
+};
+
+/// The text parts of a homebrew spell. Everything else is set with command options.
+#[derive(Debug, Modal)]
+#[name = "Homebrew spell"]
+struct SpellModal {
+	#[name = "Name"]
+	#[min_length = 1]
+	#[max_length = 100]
+	name: String,
+	#[name = "Casting time"]
+	#[placeholder = "1 action"]
+	#[max_length = 100]
+	casting_time: String,
+	#[name = "Range"]
+	#[placeholder = "60 feet"]
+	#[max_length = 100]
+	range: String,
+	#[name = "Duration"]
+	#[placeholder = "Instantaneous"]
+	#[max_length = 100]
+	duration: String,
+	#[name = "Description"]
+	#[paragraph]
+	#[max_length = 4000]
+	description: String,
+}
+
+impl HomebrewSpell {
+	pub fn to_spell(&self) -> Spell {
+		Spell {
+			name: self.name.clone(),
+			level: self.level,
+			school: self.school.clone().into(),
+			classes: self
+				.classes
+				.split(',')
+				.map(str::trim)
+				.filter(|class| !class.is_empty())
+				.map(String::from)
+				.collect(),
+			description: self.description.clone(),
+			ritual: self.ritual,
+			concentration: self.concentration,
+			casting_time: self.casting_time.clone(),
+			range: self.range.clone(),
+			components: self.components.clone(),
+			duration: self.duration.clone(),
+			source: SpellSource::default(),
+		}
+	}
+}
+
+/// Loads a guild's homebrew spells as a tome for the spell map.
+pub fn homebrew_collection(conn: &mut MysqlConnection, guild: u64) -> QueryResult<SpellCollection> {
+	let spells = HomebrewSpells::table
+		.filter(HomebrewSpells::guild.eq(guild))
+		.load::<HomebrewSpell>(conn)?
+		.iter()
+		.map(HomebrewSpell::to_spell)
+		.collect();
+
+	Ok(SpellCollection::homebrew(guild, spells))
+}
+
+/// Normalises a comma separated class list, e.g. "wizard,  sorcerer" to "Wizard, Sorcerer".
+fn class_list(classes: &str) -> String {
+	classes
+		.split(',')
+		.map(str::trim)
+		.filter(|class| !class.is_empty())
+		.map(|class| {
+			let mut chars = class.chars();
+			chars.next().map_or_else(String::new, |first| {
+				first
+					.to_uppercase()
+					.chain(chars.flat_map(char::to_lowercase))
+					.collect()
+			})
+		})
+		.join(", ")
+}
+
+async fn autocomplete_homebrew<'a>(
+	ctx: Context<'_>,
+	partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+	let mut conn = ctx.data().db.lock().await;
+	let serenity::GuildId(guild_id) = ctx.guild_id().unwrap_or_default();
+
+	let names = HomebrewSpells::table
+		.filter(HomebrewSpells::guild.eq(guild_id))
+		.select(HomebrewSpells::name)
+		.order(HomebrewSpells::name)
+		.load::<String>(&mut *conn)
+		.unwrap_or_default();
+
+	futures::stream::iter(names)
+		.filter(move |name| {
+			futures::future::ready(name.to_lowercase().contains(&partial.to_lowercase()))
+		})
+		.take(25)
+}
+
+fn find_homebrew(
+	conn: &mut MysqlConnection,
+	guild_id: u64,
+	name: &str,
+) -> QueryResult<Option<HomebrewSpell>> {
+	HomebrewSpells::table
+		.filter(HomebrewSpells::guild.eq(guild_id))
+		.filter(HomebrewSpells::name.eq(name))
+		.first::<HomebrewSpell>(conn)
+		.optional()
+}
+
+/// Records the current state of a spell in its edit history.
+fn record_edit(
+	conn: &mut MysqlConnection,
+	spell: &HomebrewSpell,
+	editor: u64,
+) -> Result<(), Error> {
+	let content = serde_json::to_string(&spell.to_spell())?;
+	let created = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+	diesel::insert_into(HomebrewSpellEdits::table)
+		.values(&NewHomebrewSpellEdit {
+			spell: spell.id,
+			editor,
+			created,
+			content: &content,
+		})
+		.execute(conn)?;
+
+	Ok(())
+}
+
+/// Rebuilds the spell map so homebrew changes show up straight away.
+async fn refresh_spell_map(ctx: ApplicationContext<'_>) {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let sm = build_spell_map(guild_id, ctx.data().db.clone()).await;
+	ctx.data().spell_map.write().await.insert(guild_id, sm);
+}
+
+/// Create and edit this guild's own homebrew spells.
+#[poise::command(
+	slash_command,
+	guild_only,
+	subcommands(
+		"create_homebrew",
+		"edit_homebrew",
+		"delete_homebrew",
+		"list_homebrew",
+		"homebrew_history"
+	)
+)]
+#[allow(clippy::unused_async)]
+pub async fn homebrew(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+/// Create a homebrew spell. Name, description and mechanics are entered in a form.
+#[poise::command(slash_command, guild_only, rename = "create", check = "is_manager")]
+async fn create_homebrew(
+	ctx: ApplicationContext<'_>,
+	#[description = "Spell level (0 for cantrips)"]
+	#[max = 9]
+	level: u8,
+	#[description = "Spell school"] school: SpellSchool,
+	#[description = "Classes, separated by commas"]
+	#[max_length = 255]
+	classes: String,
+	#[description = "Components, e.g. V, S, M (a feather)"]
+	#[max_length = 255]
+	components: Option<String>,
+	#[description = "Can be cast as a ritual"]
+	#[flag]
+	ritual: bool,
+	#[description = "Requires concentration"]
+	#[flag]
+	concentration: bool,
+) -> Result<(), Error> {
+	let Some(form) = SpellModal::execute(ctx).await? else {
+		return Ok(());
+	};
+
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let serenity::UserId(editor) = ctx.author().id;
+
+	{
+		let mut conn = ctx.data().db.lock().await;
+
+		if find_homebrew(&mut conn, guild_id, &form.name)?.is_some() {
+			ctx.say(format!(
+				"{} already exists, use `/homebrew edit` to change it.",
+				form.name
+			))
+			.await?;
+			return Ok(());
+		}
+
+		conn.transaction::<_, Error, _>(|conn| {
+			diesel::insert_into(HomebrewSpells::table)
+				.values(&NewHomebrewSpell {
+					guild: guild_id,
+					name: &form.name,
+					level,
+					school: school.name(),
+					classes: &class_list(&classes),
+					ritual,
+					concentration,
+					casting_time: &form.casting_time,
+					range: &form.range,
+					components: components.as_deref().unwrap_or_default(),
+					duration: &form.duration,
+					description: &form.description,
+				})
+				.execute(conn)?;
+
+			let spell = find_homebrew(conn, guild_id, &form.name)?.ok_or("Spell not saved")?;
+			record_edit(conn, &spell, editor)
+		})?;
+	}
+
+	refresh_spell_map(ctx).await;
+	ctx.say(format!("Created homebrew spell {}.", form.name))
+		.await?;
+	super::settings::announce(
+		ctx.into(),
+		&format!("New homebrew spell: **{}**", form.name),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Edit a homebrew spell. Options that are left out keep their current value.
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, guild_only, rename = "edit", check = "is_manager")]
+async fn edit_homebrew(
+	ctx: ApplicationContext<'_>,
+	#[description = "Spell to edit"]
+	#[autocomplete = "autocomplete_homebrew"]
+	spell: String,
+	#[description = "Spell level (0 for cantrips)"]
+	#[max = 9]
+	level: Option<u8>,
+	#[description = "Spell school"] school: Option<SpellSchool>,
+	#[description = "Classes, separated by commas"]
+	#[max_length = 255]
+	classes: Option<String>,
+	#[description = "Components, e.g. V, S, M (a feather)"]
+	#[max_length = 255]
+	components: Option<String>,
+	#[description = "Can be cast as a ritual"] ritual: Option<bool>,
+	#[description = "Requires concentration"] concentration: Option<bool>,
+) -> Result<(), Error> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let serenity::UserId(editor) = ctx.author().id;
+
+	let Some(mut homebrew) = find_homebrew(&mut *ctx.data().db.lock().await, guild_id, &spell)?
+	else {
+		ctx.say(format!("There is no homebrew spell named {spell}."))
+			.await?;
+		return Ok(());
+	};
+
+	let defaults = SpellModal {
+		name: homebrew.name.clone(),
+		casting_time: homebrew.casting_time.clone(),
+		range: homebrew.range.clone(),
+		duration: homebrew.duration.clone(),
+		description: homebrew.description.clone(),
+	};
+	let Some(form) = poise::execute_modal(ctx, Some(defaults), None).await? else {
+		return Ok(());
+	};
+
+	{
+		let mut conn = ctx.data().db.lock().await;
+
+		if !form.name.eq(&homebrew.name)
+			&& find_homebrew(&mut conn, guild_id, &form.name)?.is_some()
+		{
+			ctx.say(format!("{} already exists.", form.name)).await?;
+			return Ok(());
+		}
+
+		homebrew.name = form.name;
+		homebrew.casting_time = form.casting_time;
+		homebrew.range = form.range;
+		homebrew.duration = form.duration;
+		homebrew.description = form.description;
+		if let Some(level) = level {
+			homebrew.level = level;
+		}
+		if let Some(school) = school {
+			homebrew.school = school.name().to_string();
+		}
+		if let Some(classes) = classes {
+			homebrew.classes = class_list(&classes);
+		}
+		if let Some(components) = components {
+			homebrew.components = components;
+		}
+		if let Some(ritual) = ritual {
+			homebrew.ritual = ritual;
+		}
+		if let Some(concentration) = concentration {
+			homebrew.concentration = concentration;
+		}
+
+		conn.transaction::<_, Error, _>(|conn| {
+			homebrew.save_changes::<HomebrewSpell>(conn)?;
+			record_edit(conn, &homebrew, editor)
+		})?;
+	}
+
+	refresh_spell_map(ctx).await;
+	ctx.say(format!("Saved homebrew spell {}.", homebrew.name))
+		.await?;
+	super::settings::announce(
+		ctx.into(),
+		&format!("Homebrew spell updated: **{}**", homebrew.name),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Delete a homebrew spell and its edit history.
+#[poise::command(slash_command, guild_only, rename = "delete", check = "is_manager")]
+async fn delete_homebrew(
+	ctx: ApplicationContext<'_>,
+	#[description = "Spell to delete"]
+	#[autocomplete = "autocomplete_homebrew"]
+	spell: String,
+) -> Result<(), Error> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+
+	let count = diesel::delete(
+		HomebrewSpells::table
+			.filter(HomebrewSpells::guild.eq(guild_id))
+			.filter(HomebrewSpells::name.eq(&spell)),
+	)
+	.execute(&mut *ctx.data().db.lock().await)?;
+
+	if count > 0 {
+		refresh_spell_map(ctx).await;
+		ctx.say(format!("Deleted homebrew spell {spell}.")).await?;
+		super::settings::announce(ctx.into(), &format!("Homebrew spell removed: **{spell}**"))
+			.await;
+	} else {
+		ctx.say(format!("There is no homebrew spell named {spell}."))
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// List this guild's homebrew spells.
+#[poise::command(slash_command, guild_only, rename = "list")]
+async fn list_homebrew(ctx: Context<'_>) -> Result<(), Error> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+
+	let spells = HomebrewSpells::table
+		.filter(HomebrewSpells::guild.eq(guild_id))
+		.load::<HomebrewSpell>(&mut *ctx.data().db.lock().await)?
+		.iter()
+		.map(HomebrewSpell::to_spell)
+		.collect_vec();
+
+	if spells.is_empty() {
+		ctx.say("This guild has no homebrew spells yet.").await?;
+		return Ok(());
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed.title("Homebrew spells");
+	super::send_paginated_message(ctx, super::spells::level_lines(spells.iter()), embed, false)
+		.await?;
+
+	Ok(())
+}
+
+/// Show who edited a homebrew spell and when.
+#[poise::command(slash_command, guild_only, rename = "history")]
+async fn homebrew_history(
+	ctx: Context<'_>,
+	#[description = "Spell"]
+	#[autocomplete = "autocomplete_homebrew"]
+	spell: String,
+) -> Result<(), Error> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+
+	let edits = {
+		let mut conn = ctx.data().db.lock().await;
+		let Some(homebrew) = find_homebrew(&mut conn, guild_id, &spell)? else {
+			ctx.say(format!("There is no homebrew spell named {spell}."))
+				.await?;
+			return Ok(());
+		};
+
+		HomebrewSpellEdits::table
+			.filter(HomebrewSpellEdits::spell.eq(homebrew.id))
+			.order(HomebrewSpellEdits::id.desc())
+			.select((
+				HomebrewSpellEdits::editor,
+				HomebrewSpellEdits::created,
+				HomebrewSpellEdits::content,
+			))
+			.load::<HomebrewSpellEdit>(&mut *conn)?
+	};
+
+	let mut lines = Vec::new();
+	let mut edits = edits.iter().peekable();
+	while let Some(edit) = edits.next() {
+		let current: Spell = serde_json::from_str(&edit.content)?;
+		let changes = match edits.peek() {
+			Some(previous) => {
+				let previous: Spell = serde_json::from_str(&previous.content)?;
+				changed_fields(&previous, &current)
+			}
+			None => "created".to_string(),
+		};
+		lines.push(format!(
+			"<t:{}:R> by <@{}>: {changes}",
+			edit.created, edit.editor
+		));
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed.title(format!("History of {spell}"));
+	super::send_paginated_message(
+		ctx,
+		lines.into_iter().map(Into::into).collect(),
+		embed,
+		false,
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// Names the fields that differ between two versions of a spell.
+fn changed_fields(previous: &Spell, current: &Spell) -> String {
+	let fields = [
+		("name", previous.name != current.name),
+		("level", previous.level != current.level),
+		("school", previous.school != current.school),
+		("classes", previous.classes != current.classes),
+		("ritual", previous.ritual != current.ritual),
+		(
+			"concentration",
+			previous.concentration != current.concentration,
+		),
+		(
+			"casting time",
+			previous.casting_time != current.casting_time,
+		),
+		("range", previous.range != current.range),
+		("components", previous.components != current.components),
+		("duration", previous.duration != current.duration),
+		("description", previous.description != current.description),
+	];
+
+	let changed = fields
+		.iter()
+		.filter(|(_, changed)| *changed)
+		.map(|(field, _)| field)
+		.join(", ");
+	if changed.is_empty() {
+		"no changes".to_string()
+	} else {
+		changed
+	}
+}
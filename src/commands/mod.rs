@@ -1,21 +1,24 @@
 use convert_case::Casing;
 use futures::{Stream, StreamExt};
-use poise::serenity_prelude::{
-	self as serenity, CacheHttp, CreateComponents, CreateEmbed, ReactionType,
-};
-use std::{
-	sync::{
-		atomic::{AtomicUsize, Ordering},
-		Arc,
-	},
-	time::Duration,
-};
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, CacheHttp};
 
 use crate::{data::SpellSchool, Context, Error};
 
+mod concentration;
+mod homebrew;
+mod pagination;
+mod random;
+mod scroll;
+mod settings;
+mod slots;
+mod spell;
+mod spellbook;
 pub mod spells;
 mod tomes;
 
+pub use pagination::{send_paginated_message, send_spell_list, Line};
+pub use settings::{dynamic_prefix, load_all_settings};
 pub use spells::build_spell_map;
 
 /// Show this menu
@@ -51,6 +54,50 @@ async fn autocomplete_class<'a>(
 		.map(|class| class.to_case(convert_case::Case::Title))
 }
 
+async fn autocomplete_spell<'a>(
+	ctx: Context<'_>,
+	partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+	let spell_map = ctx.data().spell_map.read().await;
+
+	let id = ctx.guild_id().unwrap_or_default();
+	let partial_lower = partial.to_lowercase();
+	let vec: Vec<String> = spell_map.get(&id).map_or(Vec::new(), |sm| {
+		sm.get_all_spells()
+			.iter()
+			.filter(|spell| spell.name.to_lowercase().contains(&partial_lower))
+			.map(|spell| spell.name.clone())
+			.take(25)
+			.collect()
+	});
+
+	futures::stream::iter(vec)
+}
+
+/// Books and tomes the guild's spells come from.
+async fn autocomplete_source<'a>(
+	ctx: Context<'_>,
+	partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+	let spell_map = ctx.data().spell_map.read().await;
+
+	let id = ctx.guild_id().unwrap_or_default();
+	let partial_lower = partial.to_lowercase();
+	let vec: Vec<String> = spell_map.get(&id).map_or(Vec::new(), |sm| {
+		sm.get_all_spells()
+			.iter()
+			.map(|spell| &spell.source.book)
+			.filter(|book| book.to_lowercase().contains(&partial_lower))
+			.unique()
+			.sorted()
+			.take(25)
+			.cloned()
+			.collect()
+	});
+
+	futures::stream::iter(vec)
+}
+
 #[allow(clippy::unused_async)]
 async fn autocomplete_level(
 	_ctx: Context<'_>,
@@ -72,124 +119,77 @@ pub fn commands() -> Vec<poise::Command<crate::Data, crate::Error>> {
 		help(),
 		tomes::tomes(),
 		spells::spell_list_slash(),
+		random::random_spells(),
 		spells::spell_list_prefix(),
 		spells::rebuild(),
+		spellbook::spellbook(),
+		slots::slots(),
+		slots::rest(),
+		concentration::concentrate(),
+		concentration::concentration(),
+		scroll::scroll(),
+		spell::spell(),
+		homebrew::homebrew(),
+		settings::settings(),
 	]
 }
 
-pub async fn is_manager(ctx: Context<'_>) -> Result<bool, Error> {
-	let author = ctx.author_member().await;
-
-	if let Some(author) = author && let Some(cache) = ctx.cache() {
-		let role = author.roles(cache)
-			.and_then(|roles| roles.into_iter().find(|role| role.name.eq("Server Brewer")))
-			.is_some();
-
-		let res = role || author.permissions(cache)?.manage_guild() || author.user.id.eq(&302379230308859905u64);
-
-		if !res {
-			ctx.say("You don't have permission to use this command.").await?;
-		}
-		Ok(res)
-	} else {
-		Ok(false)
-	}
-}
-
-pub async fn send_paginated_message(
-	ctx: Context<'_>,
-	pages: Vec<String>,
-	mut embed: CreateEmbed,
+pub async fn event_handler(
+	ctx: &serenity::Context,
+	event: &poise::Event<'_>,
+	_framework: poise::FrameworkContext<'_, crate::Data, crate::Error>,
+	data: &crate::Data,
 ) -> Result<(), Error> {
-	fn mk_btns<'a>(
-		c: &'a mut CreateComponents,
-		len: usize,
-		page_index: &Arc<AtomicUsize>,
-	) -> &'a mut CreateComponents {
-		c.create_action_row(|r| {
-			let i = page_index.load(Ordering::Relaxed);
-			if i > 0 {
-				r.create_button(|b| {
-					b.custom_id("interact.prev")
-						.label("Previous")
-						.style(serenity::ButtonStyle::Primary)
-						.emoji(ReactionType::Unicode("⬅️".to_string()))
-				});
-			}
-			if i < len - 1 {
-				r.create_button(|b| {
-					b.custom_id("interact.next")
-						.label("Next")
-						.style(serenity::ButtonStyle::Primary)
-						.emoji(ReactionType::Unicode("➡️".to_string()))
-				});
-			}
-			r
-		})
+	match event {
+		poise::Event::Message { new_message } => {
+			concentration::on_message(ctx, new_message, data).await?;
+		}
+		poise::Event::InteractionCreate {
+			interaction: serenity::Interaction::MessageComponent(interaction),
+		} => pagination::on_component(ctx, interaction, data).await?,
+		poise::Event::InteractionCreate {
+			interaction: serenity::Interaction::ModalSubmit(interaction),
+		} => pagination::on_modal(ctx, interaction, data).await?,
+		_ => {}
 	}
 
-	let page_index = Arc::new(AtomicUsize::new(0));
-	let pages = Arc::new(pages);
+	Ok(())
+}
 
-	embed
-		.description(pages.get(0).unwrap())
-		.footer(|f| f.text(format!("Page {} out of {}", 1, pages.len())));
+/// Guilds that haven't picked a manager role keep using the role the bot always looked for.
+pub const DEFAULT_MANAGER_ROLE: &str = "Server Brewer";
 
-	let reply = ctx
-		.send(|m| {
-			m.embeds.push(embed.clone());
-			if pages.len() > 1 {
-				m.components(|c| mk_btns(c, pages.len(), &page_index));
-			}
-			m.ephemeral(true)
-		})
-		.await?;
-
-	let mut interactions = reply
-		.message()
-		.await?
-		.await_component_interactions(ctx)
-		.timeout(Duration::from_secs(10 * 60))
-		.author_id(ctx.author().id)
-		.build();
-
-	while let Some(interaction) = interactions.next().await {
-		let mut embed = embed.clone();
-		let page_index = page_index.clone();
-		let pages = pages.clone();
-
-		let mut i = page_index.load(Ordering::Relaxed);
-		match interaction.data.custom_id.as_str() {
-			"interact.prev" => {
-				i -= 1;
-			}
-			"interact.next" => {
-				i += 1;
-			}
-			_ => {}
+/// Managers are the bot's owners, and members with the guild's manager role (or a role named
+/// [`DEFAULT_MANAGER_ROLE`] if it has none) or the Manage Server permission.
+pub async fn is_manager(ctx: Context<'_>) -> Result<bool, Error> {
+	let owner = ctx.framework().options().owners.contains(&ctx.author().id);
+	let manager_role = match ctx.guild_id() {
+		Some(guild_id) => {
+			settings::guild_settings(ctx.data(), guild_id)
+				.await
+				.manager_role
 		}
-		page_index.store(i, Ordering::Relaxed);
+		None => None,
+	};
+	let author = ctx.author_member().await;
 
-		if let Some(desc) = pages.get(i) {
-			embed
-				.description(desc)
-				.footer(|f| f.text(format!("Page {} out of {}", i + 1, pages.len())));
-		}
+	let res = owner
+		|| match (author, ctx.cache()) {
+			(Some(author), Some(cache)) => {
+				let role = match manager_role {
+					Some(role) => author.roles.contains(&serenity::RoleId(role)),
+					None => author.roles(cache).is_some_and(|roles| {
+						roles.iter().any(|role| role.name == DEFAULT_MANAGER_ROLE)
+					}),
+				};
+				role || author.permissions(cache)?.manage_guild()
+			}
+			_ => false,
+		};
 
-		interaction
-			.create_interaction_response(ctx, |r| {
-				r.kind(serenity::InteractionResponseType::UpdateMessage)
-					.interaction_response_data(|d| {
-						let mut components = CreateComponents::default();
-						mk_btns(&mut components, pages.len(), &page_index);
-
-						d.set_embed(embed)
-							.set_components(components)
-							.ephemeral(true)
-					})
-			})
+	if !res {
+		ctx.say("You don't have permission to use this command.")
 			.await?;
 	}
-
-	Ok(())
+	Ok(res)
 }
@@ -0,0 +1,773 @@
+use diesel::{
+	prelude::*,
+	result::{DatabaseErrorKind, Error as DieselError},
+};
+use itertools::Itertools;
+use poise::serenity_prelude::{
+	self as serenity, ActionRowComponent, CreateComponents, CreateEmbed,
+	MessageComponentInteraction, ModalSubmitInteraction, ReactionType,
+};
+use serde::{Deserialize, Serialize};
+
+use super::spells::SpellFilter;
+use crate::{
+	data::{Spell, SpellSchool},
+	models::{NewPaginatedMessage, PaginatedMessage},
+	schema::PaginatedMessages,
+	Context, Data, Error,
+};
+
+/// Discord's limit on the length of an embed description.
+const DESCRIPTION_LIMIT: usize = 4096;
+/// Discord allows at most 25 options in a select menu.
+const MAX_OPTIONS: usize = 25;
+
+/// Component ids of paginated messages look like `page:<id>:<action>:<page>`, where `id` keys
+/// the lines in the `PaginatedMessages` table. Everything needed to turn the page is in the id,
+/// so buttons keep working across restarts.
+const PREFIX: &str = "page:";
+const GOTO: &str = "goto";
+const SPELL: &str = "spell";
+/// Filter controls of spell lists, which rebuild the list from its stored filter.
+const LEVELS: &str = "levels";
+const SCHOOLS: &str = "schools";
+const RITUAL: &str = "ritual";
+const CONCENTRATION: &str = "concentration";
+
+/// A line of a paginated message. Lines standing for a spell can be picked to show its details,
+/// and headers are repeated at the top of the next page when their group goes on there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Line {
+	Text(String),
+	Spell { text: String, spell: String },
+	Header { header: String },
+}
+
+impl Line {
+	pub fn header(text: String) -> Self {
+		Self::Header { header: text }
+	}
+
+	fn text(&self) -> &str {
+		match self {
+			Self::Text(text) | Self::Spell { text, .. } | Self::Header { header: text } => text,
+		}
+	}
+
+	fn spell(&self) -> Option<&str> {
+		match self {
+			Self::Spell { spell, .. } => Some(spell),
+			Self::Text(_) | Self::Header { .. } => None,
+		}
+	}
+}
+
+impl From<String> for Line {
+	fn from(value: String) -> Self {
+		Self::Text(value)
+	}
+}
+
+impl From<&Spell> for Line {
+	fn from(value: &Spell) -> Self {
+		Self::Spell {
+			text: value.label(),
+			spell: value.name.clone(),
+		}
+	}
+}
+
+const CONTINUED: &str = " *(continued)*";
+
+struct Page<'a> {
+	/// Header of the group carried over from the previous page.
+	continued: Option<&'a str>,
+	lines: &'a [Line],
+}
+
+/// Splits lines into pages of at most `page_size` lines, starting a new page early when the
+/// next line would push the page past the embed description limit. A repeated header counts as
+/// one of the page's lines.
+fn paginate(lines: &[Line], page_size: usize) -> Vec<Page<'_>> {
+	let mut pages = Vec::new();
+	let mut start = 0;
+	let mut continued = None;
+	let mut header = None;
+	let mut count = 0;
+	let mut length = 0;
+
+	for (i, line) in lines.iter().enumerate() {
+		if count > 0 && (count == page_size || length + line.text().len() > DESCRIPTION_LIMIT) {
+			pages.push(Page {
+				continued,
+				lines: &lines[start..i],
+			});
+			start = i;
+			continued = header.filter(|_| !matches!(line, Line::Header { .. }));
+			count = usize::from(continued.is_some());
+			length = continued.map_or(0, |header: &str| header.len() + CONTINUED.len() + 1);
+		}
+		if let Line::Header { header: text } = line {
+			header = Some(text.as_str());
+		}
+		count += 1;
+		length += line.text().len() + 1;
+	}
+	if start < lines.len() || pages.is_empty() {
+		pages.push(Page {
+			continued,
+			lines: &lines[start..],
+		});
+	}
+	pages
+}
+
+/// Discord allows at most five action rows per message: page buttons, up to three rows of filter
+/// controls for spell lists, and the spell details menu.
+fn components<'a>(
+	c: &'a mut CreateComponents,
+	id: u64,
+	page: usize,
+	pages: &[Page],
+	filter: Option<&SpellFilter>,
+) -> &'a mut CreateComponents {
+	let len = pages.len();
+	if len > 1 {
+		c.create_action_row(|r| {
+			for (button, label, emoji, target) in [
+				("first", "First", "⏮️", 0),
+				("prev", "Previous", "⬅️", page.saturating_sub(1)),
+				(GOTO, "Go to", "🔢", page),
+				("next", "Next", "➡️", (page + 1).min(len - 1)),
+				("last", "Last", "⏭️", len - 1),
+			] {
+				r.create_button(|b| {
+					b.custom_id(format!("{PREFIX}{id}:{button}:{target}"))
+						.label(label)
+						.style(serenity::ButtonStyle::Primary)
+						.emoji(ReactionType::Unicode(emoji.to_string()))
+						.disabled(button != GOTO && target == page)
+				});
+			}
+			r
+		});
+	}
+
+	if let Some(filter) = filter {
+		filter_components(c, id, filter);
+	}
+
+	let spells = pages[page]
+		.lines
+		.iter()
+		.filter_map(Line::spell)
+		.unique()
+		.take(MAX_OPTIONS)
+		.collect_vec();
+	if !spells.is_empty() {
+		c.create_action_row(|r| {
+			r.create_select_menu(|m| {
+				m.custom_id(format!("{PREFIX}{id}:{SPELL}:{page}"))
+					.placeholder("Show spell details")
+					.options(|o| {
+						for spell in spells {
+							o.create_option(|opt| opt.label(spell).value(spell));
+						}
+						o
+					})
+			})
+		});
+	}
+	c
+}
+
+fn filter_components<'a>(
+	c: &'a mut CreateComponents,
+	id: u64,
+	filter: &SpellFilter,
+) -> &'a mut CreateComponents {
+	c.create_action_row(|r| {
+		for (toggle, label, on) in [
+			(RITUAL, "Ritual only", filter.ritual),
+			(CONCENTRATION, "Concentration only", filter.concentration),
+		] {
+			r.create_button(|b| {
+				b.custom_id(format!("{PREFIX}{id}:{toggle}"))
+					.label(label)
+					.style(if on {
+						serenity::ButtonStyle::Success
+					} else {
+						serenity::ButtonStyle::Secondary
+					})
+			});
+		}
+		r
+	})
+	.create_action_row(|r| {
+		r.create_select_menu(|m| {
+			m.custom_id(format!("{PREFIX}{id}:{LEVELS}"))
+				.placeholder("Any level, pick levels to list a range")
+				.min_values(0)
+				.max_values(10)
+				.options(|o| {
+					for level in 0..=9u8 {
+						let selected = filter.min_level.is_some_and(|min| level >= min)
+							&& filter.max_level.is_some_and(|max| level <= max);
+						o.create_option(|opt| {
+							opt.label(if level == 0 {
+								"Cantrips".to_string()
+							} else {
+								format!("Level {level}")
+							})
+							.value(level)
+							.default_selection(selected)
+						});
+					}
+					o
+				})
+		})
+	})
+	.create_action_row(|r| {
+		r.create_select_menu(|m| {
+			m.custom_id(format!("{PREFIX}{id}:{SCHOOLS}"))
+				.placeholder("Any school")
+				.min_values(0)
+				.max_values(8)
+				.options(|o| {
+					for school in SpellSchool::all() {
+						o.create_option(|opt| {
+							opt.label(school.name())
+								.value(school.name())
+								.default_selection(filter.schools.contains(&school))
+						});
+					}
+					o
+				})
+		})
+	})
+}
+
+/// Applies a change made with the filter controls: picked levels become the range between the
+/// lowest and highest of them, and the toggles flip.
+fn update_filter(filter: &mut SpellFilter, control: &str, values: &[String]) {
+	match control {
+		LEVELS => {
+			let levels = values.iter().filter_map(|value| value.parse::<u8>().ok());
+			filter.min_level = levels.clone().min();
+			filter.max_level = levels.max();
+		}
+		SCHOOLS => {
+			filter.schools = values.iter().cloned().map(SpellSchool::from).collect();
+		}
+		RITUAL => filter.ritual = !filter.ritual,
+		CONCENTRATION => filter.concentration = !filter.concentration,
+		_ => {}
+	}
+}
+
+fn show_page(embed: &mut CreateEmbed, pages: &[Page], page: usize) {
+	let Page { continued, lines } = pages[page];
+	embed
+		.description(if lines.is_empty() {
+			"*Nothing to show.*".to_string()
+		} else {
+			continued
+				.map(|header| format!("{header}{CONTINUED}"))
+				.into_iter()
+				.chain(lines.iter().map(|line| line.text().to_string()))
+				.join("\n")
+		})
+		.footer(|f| f.text(format!("Page {} out of {}", page + 1, pages.len())));
+}
+
+/// Sends lines as an embed with one page at a time, sized to the author's page size setting.
+/// Public messages are posted for the whole channel, and unless the guild shares pages, only the
+/// author can turn them.
+pub async fn send_paginated_message(
+	ctx: Context<'_>,
+	lines: Vec<Line>,
+	embed: CreateEmbed,
+	public: bool,
+) -> Result<(), Error> {
+	send(ctx, lines, embed, None, public).await
+}
+
+/// Sends a spell list along with controls to change its filter in place.
+pub async fn send_spell_list(
+	ctx: Context<'_>,
+	lines: Vec<Line>,
+	filter: &SpellFilter,
+	public: bool,
+) -> Result<(), Error> {
+	send(ctx, lines, CreateEmbed::default(), Some(filter), public).await
+}
+
+async fn send(
+	ctx: Context<'_>,
+	lines: Vec<Line>,
+	mut embed: CreateEmbed,
+	filter: Option<&SpellFilter>,
+	public: bool,
+) -> Result<(), Error> {
+	let title = embed
+		.0
+		.get("title")
+		.and_then(serde_json::Value::as_str)
+		.map(String::from);
+	let shared_pages = match ctx.guild_id() {
+		Some(guild_id) => {
+			super::settings::guild_settings(ctx.data(), guild_id)
+				.await
+				.shared_pages
+		}
+		None => true,
+	};
+	let content = serde_json::to_string(&lines)?;
+	let spell_filter = filter.map(serde_json::to_string).transpose()?;
+
+	let (id, page_size) = {
+		let mut conn = ctx.data().db.lock().await;
+		let author = ctx.author().id.0;
+		let page_size = super::settings::user_settings(&mut conn, author).page_size;
+
+		// Random ids can collide, if rarely; pick another one when they do.
+		let id = loop {
+			let id = rand::random::<u64>();
+			let inserted = diesel::insert_into(PaginatedMessages::table)
+				.values(&NewPaginatedMessage {
+					id,
+					page_size,
+					content: &content,
+					owner: (!shared_pages).then_some(author),
+					title: title.as_deref(),
+					spell_filter: spell_filter.as_deref(),
+				})
+				.execute(&mut *conn);
+			match inserted {
+				Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {}
+				inserted => {
+					inserted?;
+					break id;
+				}
+			}
+		};
+		(id, page_size)
+	};
+
+	let pages = paginate(&lines, page_size.into());
+	show_page(&mut embed, &pages, 0);
+
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m.components(|c| components(c, id, 0, &pages, filter));
+		m.ephemeral(!public)
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// Loads a paginated message, if it is still stored.
+async fn load(data: &Data, id: u64) -> QueryResult<Option<PaginatedMessage>> {
+	PaginatedMessages::table
+		.find(id)
+		.first(&mut *data.db.lock().await)
+		.optional()
+}
+
+/// Why the user can't turn the pages of a list, if they can't.
+fn refusal(stored: &PaginatedMessage, user: u64) -> Option<String> {
+	stored
+		.owner
+		.filter(|&owner| owner != user)
+		.map(|owner| format!("Only <@{owner}> can turn the pages of this list."))
+}
+
+/// Disabled copies of a message's components, for lists that are no longer stored.
+fn disabled_components(rows: &[serenity::ActionRow]) -> CreateComponents {
+	let mut c = CreateComponents::default();
+	for row in rows {
+		c.create_action_row(|r| {
+			for component in &row.components {
+				match component {
+					ActionRowComponent::Button(button) => {
+						r.create_button(|b| {
+							b.style(button.style).disabled(true);
+							if let Some(id) = &button.custom_id {
+								b.custom_id(id);
+							}
+							if let Some(url) = &button.url {
+								b.url(url);
+							}
+							if let Some(label) = &button.label {
+								b.label(label);
+							}
+							if let Some(emoji) = &button.emoji {
+								b.emoji(emoji.clone());
+							}
+							b
+						});
+					}
+					ActionRowComponent::SelectMenu(menu) => {
+						r.create_select_menu(|m| {
+							m.disabled(true);
+							if let Some(id) = &menu.custom_id {
+								m.custom_id(id);
+							}
+							if let Some(placeholder) = &menu.placeholder {
+								m.placeholder(placeholder);
+							}
+							m.options(|o| {
+								for option in &menu.options {
+									o.create_option(|o| {
+										o.label(&option.label).value(&option.value)
+									});
+								}
+								o
+							})
+						});
+					}
+					_ => {}
+				}
+			}
+			r
+		});
+	}
+	c
+}
+
+/// The lines of a stored list, and the filter they were listed with for spell lists.
+fn contents(stored: &PaginatedMessage) -> Result<(Vec<Line>, Option<SpellFilter>), Error> {
+	let lines = serde_json::from_str(&stored.content)?;
+	let filter = stored
+		.spell_filter
+		.as_deref()
+		.map(serde_json::from_str)
+		.transpose()?;
+	Ok((lines, filter))
+}
+
+/// Embed and components showing a page of a stored list.
+fn render(
+	stored: &PaginatedMessage,
+	lines: &[Line],
+	filter: Option<&SpellFilter>,
+	page: usize,
+) -> (CreateEmbed, CreateComponents) {
+	let pages = paginate(lines, stored.page_size.into());
+	let page = page.min(pages.len() - 1);
+
+	let mut embed = CreateEmbed::default();
+	if let Some(title) = &stored.title {
+		embed.title(title);
+	}
+	show_page(&mut embed, &pages, page);
+
+	let mut c = CreateComponents::default();
+	components(&mut c, stored.id, page, &pages, filter);
+	(embed, c)
+}
+
+/// Turns the page of any paginated message, shows one of its spells, or changes the filter of a
+/// spell list, whoever clicks and however old the message is. Messages whose list is no longer
+/// stored get their components disabled.
+pub async fn on_component(
+	ctx: &serenity::Context,
+	interaction: &MessageComponentInteraction,
+	data: &Data,
+) -> Result<(), Error> {
+	let Some(args) = interaction.data.custom_id.strip_prefix(PREFIX) else {
+		return Ok(());
+	};
+	let mut args = args.split(':');
+	let id: u64 = args.next().unwrap_or_default().parse()?;
+	let action = args.next().unwrap_or_default();
+	let page = args
+		.next()
+		.and_then(|target| target.parse::<usize>().ok())
+		.unwrap_or_default();
+
+	let Some(stored) = load(data, id).await? else {
+		let c = disabled_components(&interaction.message.components);
+		interaction
+			.create_interaction_response(ctx, |r| {
+				r.kind(serenity::InteractionResponseType::UpdateMessage)
+					.interaction_response_data(|d| d.set_components(c))
+			})
+			.await?;
+		return Ok(());
+	};
+	if let Some(reason) = refusal(&stored, interaction.user.id.0) {
+		return refuse(ctx, interaction, &reason).await;
+	}
+	let (mut lines, mut filter) = contents(&stored)?;
+
+	let page = match action {
+		SPELL => {
+			let name = interaction.data.values.first().cloned().unwrap_or_default();
+			return show_spell(ctx, interaction, data, &name, id, page).await;
+		}
+		GOTO => {
+			let len = paginate(&lines, stored.page_size.into()).len();
+			return ask_page(ctx, interaction, id, page, len).await;
+		}
+		LEVELS | SCHOOLS | RITUAL | CONCENTRATION => {
+			let Some(filter) = &mut filter else {
+				return Ok(());
+			};
+			update_filter(filter, action, &interaction.data.values);
+
+			let rebuilt = match interaction.guild_id {
+				Some(guild_id) => data
+					.spell_map
+					.read()
+					.await
+					.get(&guild_id)
+					.map(|spell_map| super::spells::list_lines(spell_map, filter)),
+				None => None,
+			};
+			let Some(rebuilt) = rebuilt else {
+				return refuse(
+					ctx,
+					interaction,
+					"Spells are not loaded for this guild yet.",
+				)
+				.await;
+			};
+			lines = rebuilt;
+
+			diesel::update(PaginatedMessages::table.find(id))
+				.set((
+					PaginatedMessages::content.eq(serde_json::to_string(&lines)?),
+					PaginatedMessages::spell_filter.eq(serde_json::to_string(filter)?),
+				))
+				.execute(&mut *data.db.lock().await)?;
+			0
+		}
+		_ => page,
+	};
+
+	let (embed, c) = render(&stored, &lines, filter.as_ref(), page);
+	interaction
+		.create_interaction_response(ctx, |r| {
+			r.kind(serenity::InteractionResponseType::UpdateMessage)
+				.interaction_response_data(|d| d.set_embed(embed).set_components(c))
+		})
+		.await?;
+
+	Ok(())
+}
+
+/// Asks which page to go to, answered in [`on_modal`].
+async fn ask_page(
+	ctx: &serenity::Context,
+	interaction: &MessageComponentInteraction,
+	id: u64,
+	page: usize,
+	len: usize,
+) -> Result<(), Error> {
+	interaction
+		.create_interaction_response(ctx, |r| {
+			r.kind(serenity::InteractionResponseType::Modal)
+				.interaction_response_data(|d| {
+					d.custom_id(format!("{PREFIX}{id}:{GOTO}"))
+						.title("Go to page")
+						.components(|c| {
+							c.create_action_row(|r| {
+								r.create_input_text(|t| {
+									t.custom_id(GOTO)
+										.label(format!("Page (1-{len})"))
+										.style(serenity::InputTextStyle::Short)
+										.placeholder(page + 1)
+										.min_length(1)
+										.max_length(6)
+										.required(true)
+								})
+							})
+						})
+				})
+		})
+		.await?;
+
+	Ok(())
+}
+
+/// Goes to the page asked for in the modal of the "Go to" button.
+pub async fn on_modal(
+	ctx: &serenity::Context,
+	interaction: &ModalSubmitInteraction,
+	data: &Data,
+) -> Result<(), Error> {
+	let Some(args) = interaction.data.custom_id.strip_prefix(PREFIX) else {
+		return Ok(());
+	};
+	let id: u64 = args.split(':').next().unwrap_or_default().parse()?;
+	let page = interaction
+		.data
+		.components
+		.iter()
+		.flat_map(|row| &row.components)
+		.find_map(|component| match component {
+			ActionRowComponent::InputText(input) if input.custom_id == GOTO => {
+				input.value.trim().parse::<usize>().ok()
+			}
+			_ => None,
+		})
+		.unwrap_or(1)
+		.saturating_sub(1);
+
+	let Some(stored) = load(data, id).await? else {
+		let rows = interaction
+			.message
+			.as_ref()
+			.map_or(&[][..], |message| &message.components);
+		let c = disabled_components(rows);
+		interaction
+			.create_interaction_response(ctx, |r| {
+				r.kind(serenity::InteractionResponseType::UpdateMessage)
+					.interaction_response_data(|d| d.set_components(c))
+			})
+			.await?;
+		return Ok(());
+	};
+	if let Some(reason) = refusal(&stored, interaction.user.id.0) {
+		interaction
+			.create_interaction_response(ctx, |r| {
+				r.interaction_response_data(|d| d.content(reason).ephemeral(true))
+			})
+			.await?;
+		return Ok(());
+	}
+	let (lines, filter) = contents(&stored)?;
+
+	let (embed, c) = render(&stored, &lines, filter.as_ref(), page);
+	interaction
+		.create_interaction_response(ctx, |r| {
+			r.kind(serenity::InteractionResponseType::UpdateMessage)
+				.interaction_response_data(|d| d.set_embed(embed).set_components(c))
+		})
+		.await?;
+
+	Ok(())
+}
+
+/// Replaces the list with a spell's details, and a button leading back to the same page.
+async fn show_spell(
+	ctx: &serenity::Context,
+	interaction: &MessageComponentInteraction,
+	data: &Data,
+	name: &str,
+	id: u64,
+	page: usize,
+) -> Result<(), Error> {
+	let embed = match interaction.guild_id {
+		Some(guild_id) => data
+			.spell_map
+			.read()
+			.await
+			.get(&guild_id)
+			.and_then(|spell_map| spell_map.get_spell(name))
+			.map(super::spell::spell_embed),
+		None => None,
+	};
+	let Some(embed) = embed else {
+		return refuse(
+			ctx,
+			interaction,
+			&format!("{name} is no longer available in this guild."),
+		)
+		.await;
+	};
+
+	interaction
+		.create_interaction_response(ctx, |r| {
+			r.kind(serenity::InteractionResponseType::UpdateMessage)
+				.interaction_response_data(|d| {
+					d.set_embed(embed).components(|c| {
+						c.create_action_row(|r| {
+							r.create_button(|b| {
+								b.custom_id(format!("{PREFIX}{id}:back:{page}"))
+									.label("Back to list")
+									.style(serenity::ButtonStyle::Secondary)
+									.emoji(ReactionType::Unicode("↩️".to_string()))
+							})
+						})
+					})
+				})
+		})
+		.await?;
+
+	Ok(())
+}
+
+async fn refuse(
+	ctx: &serenity::Context,
+	interaction: &MessageComponentInteraction,
+	reason: &str,
+) -> Result<(), Error> {
+	interaction
+		.create_interaction_response(ctx, |r| {
+			r.interaction_response_data(|d| d.content(reason).ephemeral(true))
+		})
+		.await?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn text(lines: &[&str]) -> Vec<Line> {
+		lines
+			.iter()
+			.map(|line| Line::from(line.to_string()))
+			.collect()
+	}
+
+	fn sizes(pages: &[Page]) -> Vec<usize> {
+		pages.iter().map(|page| page.lines.len()).collect()
+	}
+
+	#[test]
+	fn empty_list_has_one_page() {
+		let pages = paginate(&[], 5);
+		assert_eq!(sizes(&pages), [0]);
+	}
+
+	#[test]
+	fn splits_at_page_size() {
+		assert_eq!(sizes(&paginate(&text(&["a", "b", "c"]), 3)), [3]);
+		assert_eq!(sizes(&paginate(&text(&["a", "b", "c", "d"]), 3)), [3, 1]);
+	}
+
+	#[test]
+	fn splits_before_description_limit() {
+		let long = "x".repeat(DESCRIPTION_LIMIT / 2);
+		let lines = text(&[&long, &long, "a"]);
+		assert_eq!(sizes(&paginate(&lines, 20)), [1, 2]);
+	}
+
+	#[test]
+	fn repeats_the_header_of_a_split_group() {
+		let mut lines = vec![Line::header("Evocation".to_string())];
+		lines.extend(text(&["a", "b", "c"]));
+		let pages = paginate(&lines, 2);
+
+		assert_eq!(sizes(&pages), [2, 1, 1]);
+		assert_eq!(pages[0].continued, None);
+		assert_eq!(pages[1].continued, Some("Evocation"));
+		assert_eq!(pages[2].continued, Some("Evocation"));
+	}
+
+	#[test]
+	fn does_not_repeat_a_header_that_starts_a_page() {
+		let mut lines = text(&["a", "b"]);
+		lines.push(Line::header("Evocation".to_string()));
+		lines.extend(text(&["c"]));
+		let pages = paginate(&lines, 2);
+
+		assert_eq!(sizes(&pages), [2, 2]);
+		assert_eq!(pages[1].continued, None);
+	}
+}
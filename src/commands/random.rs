@@ -0,0 +1,193 @@
+use itertools::Itertools;
+use poise::serenity_prelude::CreateEmbed;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::spells::{level_range, SpellFilter};
+use crate::{
+	data::{Spell, SpellSchool},
+	Context, Error,
+};
+
+#[derive(Debug, Clone, Copy, Default, poise::ChoiceParameter, PartialEq, Eq)]
+pub enum RandomMode {
+	#[default]
+	Uniform,
+	/// Lower level spells are picked more often.
+	#[name = "Weighted by level"]
+	WeightedByLevel,
+}
+
+impl RandomMode {
+	fn weight(self, spell: &Spell) -> u32 {
+		match self {
+			Self::Uniform => 1,
+			Self::WeightedByLevel => 10 - u32::from(spell.level.min(9)),
+		}
+	}
+}
+
+/// Picks up to `count` distinct spells. Candidates are sorted by name first, so the same seed
+/// always gives the same result for the same spell map.
+pub fn pick_spells(
+	mut spells: Vec<&Spell>,
+	count: usize,
+	mode: RandomMode,
+	seed: u64,
+) -> Vec<&Spell> {
+	let mut rng = StdRng::seed_from_u64(seed);
+	spells.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+	match mode {
+		RandomMode::Uniform => spells.choose_multiple(&mut rng, count).copied().collect(),
+		RandomMode::WeightedByLevel => spells
+			.choose_multiple_weighted(&mut rng, count, |spell| mode.weight(spell))
+			.map(|chosen| chosen.copied().collect())
+			.unwrap_or_default(),
+	}
+}
+
+/// Pick random spells, with the same filters as the spell list
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, prefix_command, guild_only, rename = "random_spell")]
+pub async fn random_spells(
+	ctx: Context<'_>,
+	#[autocomplete = "super::autocomplete_class"]
+	#[description = "Class"]
+	class: Option<String>,
+	#[autocomplete = "super::autocomplete_level"]
+	#[description = "Spell level"]
+	level: Option<u8>,
+	#[autocomplete = "super::autocomplete_level"]
+	#[description = "Minimum spell level"]
+	min_level: Option<u8>,
+	#[autocomplete = "super::autocomplete_level"]
+	#[description = "Maximum spell level"]
+	max_level: Option<u8>,
+	#[description = "Filter spell schools"] spell_school: Option<SpellSchool>,
+	#[description = "Only pick ritual spells"]
+	#[flag]
+	ritual: bool,
+	#[description = "Number of spells to pick"]
+	#[min = 1]
+	#[max = 25]
+	count: Option<u8>,
+	#[description = "Seed, to reproduce an earlier result"] seed: Option<u64>,
+	#[description = "How spells are picked"] mode: Option<RandomMode>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let (min_level, max_level) = level_range(level, min_level, max_level);
+
+	let filter = SpellFilter {
+		class,
+		min_level,
+		max_level,
+		ritual,
+		schools: spell_school.into_iter().collect(),
+		..Default::default()
+	};
+	let seed = seed.unwrap_or_else(|| rand::random::<u32>().into());
+	let mode = mode.unwrap_or_default();
+
+	let embed = {
+		let spell_map_map = ctx.data().spell_map.read().await;
+		let spell_map = spell_map_map
+			.get(&guild_id)
+			.expect("Spell map not build for this guild yet.");
+
+		let picked = pick_spells(
+			filter.apply(spell_map),
+			count.unwrap_or(1).into(),
+			mode,
+			seed,
+		);
+		if picked.is_empty() {
+			ctx.say("No spells match these filters.").await?;
+			return Ok(());
+		}
+
+		let mut embed = CreateEmbed::default();
+		embed
+			.title("Random spells")
+			.description(
+				picked
+					.iter()
+					.map(|spell| {
+						format!(
+							"**{}** (level {} {}, {})",
+							spell.name,
+							spell.level,
+							spell.school.name().to_lowercase(),
+							spell.source.book
+						)
+					})
+					.join("\n"),
+			)
+			.footer(|f| f.text(format!("Seed: {seed} · {}", mode.name())));
+		embed
+	};
+
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::SpellSource;
+
+	fn spells() -> Vec<Spell> {
+		(0..30)
+			.map(|i| Spell {
+				name: format!("Spell {i:02}"),
+				level: i % 10,
+				school: SpellSchool::Evocation,
+				classes: vec!["Wizard".to_string()],
+				description: String::new(),
+				ritual: false,
+				concentration: false,
+				casting_time: String::new(),
+				range: String::new(),
+				components: String::new(),
+				duration: String::new(),
+				source: SpellSource::default(),
+			})
+			.collect()
+	}
+
+	fn names(spells: &[&Spell]) -> Vec<String> {
+		spells.iter().map(|spell| spell.name.clone()).collect()
+	}
+
+	#[test]
+	fn same_seed_picks_the_same_spells() {
+		let spells = spells();
+		for mode in [RandomMode::Uniform, RandomMode::WeightedByLevel] {
+			let picked = names(&pick_spells(spells.iter().collect(), 5, mode, 42));
+			// The order spells come out of the spell map in doesn't matter.
+			let reversed = names(&pick_spells(spells.iter().rev().collect(), 5, mode, 42));
+
+			assert_eq!(picked.len(), 5);
+			assert_eq!(picked.iter().unique().count(), 5);
+			assert_eq!(picked, reversed);
+		}
+	}
+
+	#[test]
+	fn different_seeds_pick_different_spells() {
+		let spells = spells();
+		let pick = |seed| pick_spells(spells.iter().collect(), 5, RandomMode::Uniform, seed);
+		assert!((1..10).any(|seed| pick(seed) != pick(0)));
+	}
+
+	#[test]
+	fn picks_at_most_the_matching_spells() {
+		let spells = spells();
+		let picked = pick_spells(spells.iter().take(3).collect(), 5, RandomMode::Uniform, 7);
+		assert_eq!(picked.len(), 3);
+	}
+}
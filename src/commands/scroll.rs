@@ -0,0 +1,247 @@
+use itertools::Itertools;
+use poise::serenity_prelude::CreateEmbed;
+
+use super::{
+	random::{pick_spells, RandomMode},
+	slots::ordinal,
+	spells::{level_range, SpellFilter},
+};
+use crate::{
+	data::{
+		scroll::{Rarity, Scroll},
+		Spell, SpellSchool,
+	},
+	Context, Error,
+};
+
+/// Formats a gp amount with thousands separators.
+fn gp(amount: u32) -> String {
+	let digits = amount.to_string();
+	let grouped = digits
+		.as_bytes()
+		.rchunks(3)
+		.rev()
+		.map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+		.join(",");
+	format!("{grouped} gp")
+}
+
+fn spell_level(level: u8) -> String {
+	if level == 0 {
+		"Cantrip".to_string()
+	} else {
+		format!("{} level", ordinal(level))
+	}
+}
+
+/// A scroll of the spell, with the seed it was picked with if it was random.
+fn scroll_embed(spell: &Spell, seed: Option<u64>) -> CreateEmbed {
+	let scroll = Scroll::new(spell.level);
+	let (min_price, max_price) = scroll.rarity.price();
+	let (dmg_cost, dmg_days) = scroll.dmg_crafting();
+	let (_, dmg_min_level) = scroll.rarity.dmg_crafting();
+	let (xge_time, xge_cost) = scroll.xge_crafting();
+
+	let mut embed = CreateEmbed::default();
+	embed
+		.title(format!("Spell Scroll: {}", spell.name))
+		.field("Spell", spell_level(spell.level), true)
+		.field("Rarity", scroll.rarity.name(), true)
+		.field(
+			"Save DC / Attack",
+			format!("DC {} / +{}", scroll.save_dc, scroll.attack_bonus),
+			true,
+		)
+		.field(
+			"Buy price",
+			match max_price {
+				Some(max_price) => format!("{} – {}", gp(min_price), gp(max_price)),
+				None => format!("{}+", gp(min_price)),
+			},
+			true,
+		)
+		.field(
+			"Crafting (DMG)",
+			format!("{}, {dmg_days} days (level {dmg_min_level}+)", gp(dmg_cost)),
+			true,
+		)
+		.field(
+			"Crafting (XGE)",
+			format!("{}, {xge_time}", gp(xge_cost)),
+			true,
+		)
+		.footer(|f| {
+			f.text(match seed {
+				Some(seed) => format!("Spell source: {} · Seed: {seed}", spell.source),
+				None => format!("Spell source: {}", spell.source),
+			})
+		});
+	embed
+}
+
+/// Generate spell scrolls and scroll loot.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands("create_scroll", "scroll_loot")
+)]
+#[allow(clippy::unused_async)]
+pub async fn scroll(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+/// Create a scroll of a specific spell, or of a random spell matching the filters.
+#[allow(clippy::too_many_arguments)]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "create")]
+async fn create_scroll(
+	ctx: Context<'_>,
+	#[description = "Spell (random if omitted)"]
+	#[autocomplete = "super::autocomplete_spell"]
+	spell: Option<String>,
+	#[autocomplete = "super::autocomplete_class"]
+	#[description = "Class"]
+	class: Option<String>,
+	#[autocomplete = "super::autocomplete_level"]
+	#[description = "Spell level"]
+	level: Option<u8>,
+	#[autocomplete = "super::autocomplete_level"]
+	#[description = "Minimum spell level"]
+	min_level: Option<u8>,
+	#[autocomplete = "super::autocomplete_level"]
+	#[description = "Maximum spell level"]
+	max_level: Option<u8>,
+	#[description = "Filter spell schools"] spell_school: Option<SpellSchool>,
+	#[description = "Seed, to reproduce an earlier result"] seed: Option<u64>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let filtered = class.is_some()
+		|| level.is_some()
+		|| min_level.is_some()
+		|| max_level.is_some()
+		|| spell_school.is_some()
+		|| seed.is_some();
+	if spell.is_some() && filtered {
+		ctx.say(
+			"Filters and seeds only apply to random scrolls, leave them out when picking a spell.",
+		)
+		.await?;
+		return Ok(());
+	}
+	let seed = seed.unwrap_or_else(|| rand::random::<u32>().into());
+
+	let embed = {
+		let spell_map_map = ctx.data().spell_map.read().await;
+		let spell_map = spell_map_map
+			.get(&guild_id)
+			.expect("Spell map not build for this guild yet.");
+
+		let (picked, seed) = if let Some(spell) = spell {
+			let Some(picked) = spell_map.get_spell(&spell) else {
+				ctx.say(format!("{spell} is not available in this guild."))
+					.await?;
+				return Ok(());
+			};
+			(Some(picked), None)
+		} else {
+			let (min_level, max_level) = level_range(level, min_level, max_level);
+			let filter = SpellFilter {
+				class,
+				min_level,
+				max_level,
+				schools: spell_school.into_iter().collect(),
+				..Default::default()
+			};
+			let picked = pick_spells(filter.apply(spell_map), 1, RandomMode::Uniform, seed);
+			(picked.first().copied(), Some(seed))
+		};
+
+		let Some(picked) = picked else {
+			ctx.say("No spells match these filters.").await?;
+			return Ok(());
+		};
+
+		scroll_embed(picked, seed)
+	};
+
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// Generate a pile of random spell scrolls of a given rarity.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "loot")]
+async fn scroll_loot(
+	ctx: Context<'_>,
+	#[description = "Scroll rarity"] rarity: Rarity,
+	#[description = "Number of scrolls"]
+	#[min = 1]
+	#[max = 25]
+	count: u8,
+	#[autocomplete = "super::autocomplete_class"]
+	#[description = "Only spells from this class's spell list"]
+	class: Option<String>,
+	#[description = "Seed, to reproduce an earlier result"] seed: Option<u64>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let seed = seed.unwrap_or_else(|| rand::random::<u32>().into());
+	let (min_level, max_level) = rarity.spell_levels();
+
+	let filter = SpellFilter {
+		class,
+		min_level: Some(min_level),
+		max_level: Some(max_level),
+		..Default::default()
+	};
+
+	let embed = {
+		let spell_map_map = ctx.data().spell_map.read().await;
+		let spell_map = spell_map_map
+			.get(&guild_id)
+			.expect("Spell map not build for this guild yet.");
+
+		let picked = pick_spells(
+			filter.apply(spell_map),
+			count.into(),
+			RandomMode::Uniform,
+			seed,
+		);
+		if picked.is_empty() {
+			ctx.say("No spells match these filters.").await?;
+			return Ok(());
+		}
+
+		let mut embed = CreateEmbed::default();
+		embed
+			.title(format!("{} spell scrolls", rarity.name()))
+			.description(
+				picked
+					.iter()
+					.map(|spell| {
+						let scroll = Scroll::new(spell.level);
+						format!(
+							"**{}** ({}) · DC {} / +{}",
+							spell.name,
+							spell_level(spell.level),
+							scroll.save_dc,
+							scroll.attack_bonus
+						)
+					})
+					.join("\n"),
+			)
+			.footer(|f| f.text(format!("Seed: {seed}")));
+		embed
+	};
+
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use poise::serenity_prelude::{self as serenity, GuildId};
+
+use super::{is_manager, spells::build_spell_map, DEFAULT_MANAGER_ROLE};
+use crate::{
+	data::Edition,
+	models::{GuildSetting, UserSetting},
+	schema::{GuildSettings, UserSettings},
+	Context, Data, Error,
+};
+
+/// Lines per page of paginated messages, unless a user picks their own.
+pub const DEFAULT_PAGE_SIZE: u8 = 20;
+/// Longest command prefix a guild can pick.
+const MAX_PREFIX_LENGTH: usize = 16;
+
+impl GuildSetting {
+	fn defaults(guild: u64) -> Self {
+		Self {
+			guild,
+			edition: Edition::default().as_str().to_string(),
+			public_lists: false,
+			shared_pages: true,
+			prefix: None,
+			manager_role: None,
+			announcement_channel: None,
+			include_ua: false,
+		}
+	}
+
+	/// The edition whose version of a spell wins when both editions have it.
+	pub fn edition(&self) -> Edition {
+		self.edition.parse().unwrap_or_default()
+	}
+}
+
+/// Loads a guild's settings from the database, falling back to the defaults for guilds that
+/// never changed them. Everything but the spell map builder reads them from [`Data`] instead.
+pub fn load_settings(conn: &mut MysqlConnection, guild: u64) -> GuildSetting {
+	GuildSettings::table
+		.find(guild)
+		.first(conn)
+		.optional()
+		.unwrap_or_else(|err| {
+			log::error!("Error loading guild settings: {err}");
+			None
+		})
+		.unwrap_or_else(|| GuildSetting::defaults(guild))
+}
+
+/// Loads the settings of every guild that changed them, to keep in [`Data`].
+pub fn load_all_settings(conn: &mut MysqlConnection) -> HashMap<GuildId, GuildSetting> {
+	GuildSettings::table
+		.load::<GuildSetting>(conn)
+		.unwrap_or_else(|err| {
+			log::error!("Error loading guild settings: {err}");
+			Vec::new()
+		})
+		.into_iter()
+		.map(|settings| (GuildId(settings.guild), settings))
+		.collect()
+}
+
+/// A guild's settings, as loaded into [`Data`].
+pub async fn guild_settings(data: &Data, guild_id: GuildId) -> GuildSetting {
+	data.guild_settings
+		.read()
+		.await
+		.get(&guild_id)
+		.cloned()
+		.unwrap_or_else(|| GuildSetting::defaults(guild_id.0))
+}
+
+/// Saves a guild's settings and updates the copy in [`Data`].
+async fn save_settings(data: &Data, settings: GuildSetting) -> QueryResult<()> {
+	diesel::replace_into(GuildSettings::table)
+		.values(&settings)
+		.execute(&mut *data.db.lock().await)?;
+	data.guild_settings
+		.write()
+		.await
+		.insert(GuildId(settings.guild), settings);
+	Ok(())
+}
+
+/// The guild's own prefix for prefix commands, or the global one from `PREFIX`.
+pub fn dynamic_prefix(
+	ctx: poise::PartialContext<'_, Data, Error>,
+) -> poise::BoxFuture<'_, Result<Option<String>, Error>> {
+	Box::pin(async move {
+		let prefix = match ctx.guild_id {
+			Some(guild_id) => guild_settings(ctx.data, guild_id).await.prefix,
+			None => None,
+		};
+		Ok(Some(prefix.unwrap_or_else(|| ctx.data.prefix.clone())))
+	})
+}
+
+/// Posts a message in the guild's announcement channel, if it has one.
+pub async fn announce(ctx: Context<'_>, message: &str) {
+	let Some(guild_id) = ctx.guild_id() else {
+		return;
+	};
+	let Some(channel) = guild_settings(ctx.data(), guild_id)
+		.await
+		.announcement_channel
+	else {
+		return;
+	};
+
+	if let Err(err) = serenity::ChannelId(channel)
+		.send_message(ctx.serenity_context(), |m| {
+			m.content(message).allowed_mentions(|a| a.empty_parse())
+		})
+		.await
+	{
+		log::error!("Error announcing in channel {channel}: {err}");
+	}
+}
+
+/// Loads a user's own settings, which follow them across guilds.
+pub fn user_settings(conn: &mut MysqlConnection, user: u64) -> UserSetting {
+	UserSettings::table
+		.find(user)
+		.first(conn)
+		.optional()
+		.unwrap_or_else(|err| {
+			log::error!("Error loading user settings: {err}");
+			None
+		})
+		.unwrap_or(UserSetting {
+			user,
+			page_size: DEFAULT_PAGE_SIZE,
+		})
+}
+
+fn save_user_settings(conn: &mut MysqlConnection, settings: &UserSetting) -> QueryResult<usize> {
+	diesel::replace_into(UserSettings::table)
+		.values(settings)
+		.execute(conn)
+}
+
+/// Bot settings for this guild, and your own.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands(
+		"edition",
+		"lists",
+		"page_size",
+		"prefix",
+		"manager_role",
+		"announcements"
+	)
+)]
+#[allow(clippy::unused_async)]
+pub async fn settings(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+async fn rebuild_spell_map(ctx: Context<'_>, guild_id: GuildId) {
+	let _typing = ctx.defer_or_broadcast().await;
+	let sm = build_spell_map(guild_id, ctx.data().db.clone()).await;
+	ctx.data().spell_map.write().await.insert(guild_id, sm);
+}
+
+/// Prefer the 2014 or 2024 version of spells that are in both editions.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	ephemeral,
+	check = "is_manager"
+)]
+async fn edition(
+	ctx: Context<'_>,
+	#[description = "Edition to prefer, leave out to see the current one"] edition: Option<Edition>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let mut settings = guild_settings(ctx.data(), guild_id).await;
+
+	let Some(edition) = edition else {
+		ctx.say(format!(
+			"This guild prefers {} spells.",
+			settings.edition().as_str()
+		))
+		.await?;
+		return Ok(());
+	};
+
+	settings.edition = edition.as_str().to_string();
+	save_settings(ctx.data(), settings).await?;
+	rebuild_spell_map(ctx, guild_id).await;
+
+	ctx.say(format!(
+		"Now preferring {} spells where both editions have them.",
+		edition.as_str()
+	))
+	.await?;
+	Ok(())
+}
+
+/// Choose how spell lists are posted, who can turn their pages, and whether they include UA.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	ephemeral,
+	check = "is_manager"
+)]
+async fn lists(
+	ctx: Context<'_>,
+	#[description = "Post spell lists to the channel unless asked otherwise"] public: Option<bool>,
+	#[description = "Let everyone turn the pages of a list, not just whoever asked for it"]
+	shared_pages: Option<bool>,
+	#[description = "Include spells from Unearthed Arcana playtest documents"] include_ua: Option<
+		bool,
+	>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+
+	let mut settings = guild_settings(ctx.data(), guild_id).await;
+	if let Some(public) = public {
+		settings.public_lists = public;
+	}
+	if let Some(shared_pages) = shared_pages {
+		settings.shared_pages = shared_pages;
+	}
+	let rebuild = include_ua.is_some_and(|include_ua| include_ua != settings.include_ua);
+	if let Some(include_ua) = include_ua {
+		settings.include_ua = include_ua;
+	}
+	if public.is_some() || shared_pages.is_some() || include_ua.is_some() {
+		save_settings(ctx.data(), settings.clone()).await?;
+	}
+	if rebuild {
+		rebuild_spell_map(ctx, guild_id).await;
+	}
+
+	ctx.say(format!(
+		"Spell lists are {} by default, {} can turn their pages, and Unearthed Arcana spells are {}.",
+		if settings.public_lists {
+			"posted publicly"
+		} else {
+			"only shown to whoever asked"
+		},
+		if settings.shared_pages {
+			"everyone"
+		} else {
+			"only whoever asked"
+		},
+		if settings.include_ua {
+			"included"
+		} else {
+			"left out"
+		}
+	))
+	.await?;
+	Ok(())
+}
+
+/// Change what prefix commands start with in this guild.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	ephemeral,
+	check = "is_manager"
+)]
+async fn prefix(
+	ctx: Context<'_>,
+	#[description = "New prefix, leave out to see the current one"] prefix: Option<String>,
+	#[description = "Go back to the default prefix"]
+	#[flag]
+	reset: bool,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let mut settings = guild_settings(ctx.data(), guild_id).await;
+
+	if reset {
+		settings.prefix = None;
+	} else if let Some(prefix) = prefix {
+		if prefix.is_empty()
+			|| prefix.len() > MAX_PREFIX_LENGTH
+			|| prefix.contains(char::is_whitespace)
+		{
+			ctx.say(format!(
+				"Prefixes have 1 to {MAX_PREFIX_LENGTH} characters and no spaces."
+			))
+			.await?;
+			return Ok(());
+		}
+		settings.prefix = Some(prefix);
+	} else {
+		ctx.say(format!(
+			"Prefix commands here start with `{}`.",
+			settings.prefix.as_ref().unwrap_or(&ctx.data().prefix)
+		))
+		.await?;
+		return Ok(());
+	}
+
+	let prefix = settings
+		.prefix
+		.clone()
+		.unwrap_or_else(|| ctx.data().prefix.clone());
+	save_settings(ctx.data(), settings).await?;
+	ctx.say(format!("Prefix commands here now start with `{prefix}`."))
+		.await?;
+	Ok(())
+}
+
+/// Choose the role whose members can manage tomes, homebrew and settings in this guild.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	ephemeral,
+	check = "is_manager"
+)]
+async fn manager_role(
+	ctx: Context<'_>,
+	#[description = "Manager role, leave out to see the current one"] role: Option<serenity::Role>,
+	#[description = "Go back to the role named Server Brewer"]
+	#[flag]
+	reset: bool,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let mut settings = guild_settings(ctx.data(), guild_id).await;
+
+	let changed = reset || role.is_some();
+	if reset {
+		settings.manager_role = None;
+	} else if let Some(role) = role {
+		settings.manager_role = Some(role.id.0);
+	}
+	let manager_role = settings.manager_role;
+	if changed {
+		save_settings(ctx.data(), settings).await?;
+	}
+
+	let managers = match manager_role {
+		Some(role) => format!("Members with <@&{role}> or the Manage Server permission"),
+		None => format!(
+			"Members with a role named {DEFAULT_MANAGER_ROLE} or the Manage Server permission"
+		),
+	};
+	ctx.send(|m| {
+		m.content(format!(
+			"{managers} {} manage this guild's tomes, homebrew and settings.",
+			if changed { "can now" } else { "can" }
+		))
+		.allowed_mentions(|a| a.empty_parse())
+	})
+	.await?;
+	Ok(())
+}
+
+/// Choose a channel where changes to this guild's spells are announced.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	ephemeral,
+	check = "is_manager"
+)]
+async fn announcements(
+	ctx: Context<'_>,
+	#[description = "Announcement channel, leave out to see the current one"] channel: Option<
+		serenity::GuildChannel,
+	>,
+	#[description = "Stop announcing changes"]
+	#[flag]
+	reset: bool,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let mut settings = guild_settings(ctx.data(), guild_id).await;
+
+	let changed = reset || channel.is_some();
+	if reset {
+		settings.announcement_channel = None;
+	} else if let Some(channel) = channel {
+		settings.announcement_channel = Some(channel.id.0);
+	}
+	let announcement_channel = settings.announcement_channel;
+	if changed {
+		save_settings(ctx.data(), settings).await?;
+	}
+
+	ctx.say(match announcement_channel {
+		Some(channel) => format!("Changes to this guild's spells are announced in <#{channel}>."),
+		None => "Changes to this guild's spells are not announced.".to_string(),
+	})
+	.await?;
+	Ok(())
+}
+
+/// Set how many lines are shown per page of spell lists and other long messages.
+#[poise::command(prefix_command, slash_command, ephemeral)]
+async fn page_size(
+	ctx: Context<'_>,
+	#[description = "Lines per page, leave out to see the current size"]
+	#[min = 5]
+	#[max = 50]
+	lines: Option<u8>,
+) -> Result<(), Error> {
+	let user = ctx.author().id.0;
+	let mut conn = ctx.data().db.lock().await;
+	let mut settings = user_settings(&mut conn, user);
+
+	let Some(lines) = lines else {
+		ctx.say(format!(
+			"Your pages show up to {} lines.",
+			settings.page_size
+		))
+		.await?;
+		return Ok(());
+	};
+
+	settings.page_size = lines.clamp(5, 50);
+	save_user_settings(&mut conn, &settings)?;
+	ctx.say(format!(
+		"Your pages now show up to {} lines.",
+		settings.page_size
+	))
+	.await?;
+	Ok(())
+}
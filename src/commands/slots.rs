@@ -0,0 +1,656 @@
+use convert_case::Casing;
+use diesel::prelude::*;
+use futures::{Stream, StreamExt};
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+
+use crate::{
+	data::progression::{CasterType, Preparation, Progression},
+	models::*,
+	schema::SlotTrackers,
+	Context, Error,
+};
+
+/// Calculate and track spell slots.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands(
+		"calc_slots",
+		"track_slots",
+		"use_slot",
+		"restore_slots",
+		"slot_status",
+		"delete_tracker"
+	)
+)]
+#[allow(clippy::unused_async)]
+pub async fn slots(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+/// Take a long or short rest.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands("long_rest", "short_rest")
+)]
+#[allow(clippy::unused_async)]
+pub async fn rest(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+pub fn ordinal(n: u8) -> String {
+	let suffix = match (n % 10, n % 100) {
+		(_, 11..=13) => "th",
+		(1, _) => "st",
+		(2, _) => "nd",
+		(3, _) => "rd",
+		_ => "th",
+	};
+	format!("{n}{suffix}")
+}
+
+/// Formats a slot table as `1st: 4 · 2nd: 3 · ...`, skipping empty levels.
+pub fn format_slots(slots: &[u8]) -> String {
+	slots
+		.iter()
+		.enumerate()
+		.filter(|(_, &count)| count > 0)
+		.map(|(i, count)| format!("{}: {count}", ordinal(u8::try_from(i + 1).unwrap_or(9))))
+		.join(" · ")
+}
+
+/// Calculate spell slots and prepared spells for a class and level.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "calc")]
+async fn calc_slots(
+	ctx: Context<'_>,
+	#[autocomplete = "super::autocomplete_class"]
+	#[description = "Class"]
+	class: String,
+	#[description = "Class level"]
+	#[min = 1]
+	#[max = 20]
+	level: u8,
+	#[description = "Spellcasting ability modifier"]
+	#[min = -5]
+	#[max = 10]
+	modifier: Option<i8>,
+	#[description = "Caster type override"] caster: Option<CasterType>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let level = level.clamp(1, 20);
+
+	let mut progression = {
+		let spell_map_map = ctx.data().spell_map.read().await;
+		spell_map_map
+			.get(&guild_id)
+			.and_then(|sm| sm.get_progression(&class))
+			.cloned()
+			.unwrap_or_default()
+	};
+	if let Some(caster) = caster {
+		progression.caster = caster;
+	}
+
+	if progression == Progression::default() {
+		ctx.say(format!(
+			"{class} has no spellcasting progression. Pick a caster type to calculate slots anyway."
+		))
+		.await?;
+		return Ok(());
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed.title(format!(
+		"{} {level}",
+		class.to_case(convert_case::Case::Title)
+	));
+	add_progression_fields(&mut embed, &progression, level, modifier);
+
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+pub fn add_progression_fields(
+	embed: &mut CreateEmbed,
+	progression: &Progression,
+	level: u8,
+	modifier: Option<i8>,
+) {
+	let slots = progression.caster.slots(level);
+	if slots.iter().any(|&n| n > 0) {
+		embed.field("Spell slots", format_slots(&slots), false);
+	}
+	if let Some((count, slot_level)) = progression.caster.pact_slots(level) {
+		embed.field(
+			"Pact slots",
+			format!("{count} × {} level", ordinal(slot_level)),
+			false,
+		);
+	}
+
+	if let Some(cantrips) = progression.cantrips(level) {
+		embed.field("Cantrips known", cantrips, true);
+	}
+	match &progression.preparation {
+		Preparation::None => {}
+		Preparation::Known(_) => {
+			if let Some(known) = progression.spells(level, modifier).filter(|&n| n > 0) {
+				embed.field("Spells known", known, true);
+			}
+		}
+		Preparation::Prepared { level_divisor } => {
+			let value = progression.spells(level, modifier).map_or_else(
+				|| match level_divisor {
+					0 | 1 => "Level + ability modifier (min. 1)".to_string(),
+					d => format!("Level / {d} + ability modifier (min. 1)"),
+				},
+				|n| n.to_string(),
+			);
+			embed.field("Prepared spells", value, true);
+		}
+	}
+}
+
+impl SlotTracker {
+	fn spent_slots(&self) -> [u8; 9] {
+		let mut spent = [0; 9];
+		for (slot, count) in spent.iter_mut().zip(self.spent.split(',')) {
+			*slot = count.trim().parse().unwrap_or_default();
+		}
+		spent
+	}
+
+	fn set_spent_slots(&mut self, spent: [u8; 9]) {
+		self.spent = spent.iter().join(",");
+	}
+}
+
+async fn autocomplete_tracker<'a>(
+	ctx: Context<'_>,
+	partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+	let mut conn = ctx.data().db.lock().await;
+	let serenity::GuildId(guild_id) = ctx.guild_id().unwrap_or_default();
+	let serenity::UserId(owner_id) = ctx.author().id;
+
+	let names = SlotTrackers::table
+		.filter(SlotTrackers::guild.eq(guild_id))
+		.filter(SlotTrackers::owner.eq(owner_id))
+		.select(SlotTrackers::name)
+		.load::<String>(&mut *conn)
+		.unwrap_or_default();
+
+	futures::stream::iter(names).filter(move |name| {
+		futures::future::ready(name.to_lowercase().starts_with(&partial.to_lowercase()))
+	})
+}
+
+/// Finds the author's tracker by name, or their only tracker if no name is given.
+fn find_tracker(
+	conn: &mut MysqlConnection,
+	ctx: Context<'_>,
+	name: Option<&str>,
+) -> QueryResult<Option<SlotTracker>> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let serenity::UserId(owner_id) = ctx.author().id;
+
+	let query = SlotTrackers::table
+		.filter(SlotTrackers::guild.eq(guild_id))
+		.filter(SlotTrackers::owner.eq(owner_id));
+
+	if let Some(name) = name {
+		query
+			.filter(SlotTrackers::name.eq(name))
+			.first::<SlotTracker>(conn)
+			.optional()
+	} else {
+		let mut trackers = query.limit(2).load::<SlotTracker>(conn)?;
+		Ok(if trackers.len() == 1 {
+			trackers.pop()
+		} else {
+			None
+		})
+	}
+}
+
+async fn tracker_progression(ctx: Context<'_>, tracker: &SlotTracker) -> Progression {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+	let spell_map_map = ctx.data().spell_map.read().await;
+
+	let mut progression = spell_map_map
+		.get(&guild_id)
+		.and_then(|sm| sm.get_progression(&tracker.class))
+		.cloned()
+		.unwrap_or_default();
+	if let Some(caster) = tracker.caster.as_deref().and_then(|c| c.parse().ok()) {
+		progression.caster = caster;
+	}
+	progression
+}
+
+fn pips(available: u8, spent: u8) -> String {
+	"●".repeat(available.into()) + &"○".repeat(spent.into())
+}
+
+fn status_embed(tracker: &SlotTracker, progression: &Progression) -> CreateEmbed {
+	let max = progression.caster.slots(tracker.level);
+	let spent = tracker.spent_slots();
+
+	let mut lines: Vec<String> = max
+		.iter()
+		.zip(spent)
+		.enumerate()
+		.filter(|(_, (&max, _))| max > 0)
+		.map(|(i, (&max, spent))| {
+			let spent = spent.min(max);
+			format!(
+				"`{:>4}` {}",
+				ordinal(u8::try_from(i + 1).unwrap_or(9)),
+				pips(max - spent, spent)
+			)
+		})
+		.collect();
+
+	if let Some((count, level)) = progression.caster.pact_slots(tracker.level) {
+		let spent = tracker.pact_spent.min(count);
+		lines.push(format!(
+			"`Pact` {} ({} level)",
+			pips(count - spent, spent),
+			ordinal(level)
+		));
+	}
+	if tracker.class.eq_ignore_ascii_case("wizard") {
+		lines.push(format!(
+			"Arcane Recovery: {}",
+			if tracker.arcane_recovery {
+				"used"
+			} else {
+				"available"
+			}
+		));
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed
+		.title(format!(
+			"{} ({} {})",
+			tracker.name,
+			tracker.class.to_case(convert_case::Case::Title),
+			tracker.level
+		))
+		.description(if lines.is_empty() {
+			"No spell slots.".to_string()
+		} else {
+			lines.join("\n")
+		});
+	embed
+}
+
+async fn save_and_show(
+	ctx: Context<'_>,
+	conn: &mut MysqlConnection,
+	tracker: &SlotTracker,
+	message: String,
+) -> Result<(), Error> {
+	diesel::update(tracker).set(tracker).execute(conn)?;
+
+	let embed = status_embed(tracker, &tracker_progression(ctx, tracker).await);
+	ctx.send(|m| {
+		m.content(message);
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+const NO_TRACKER: &str =
+	"No slot tracker found. Create one with `/slots track`, or give the character's name.";
+
+/// Start (or update) tracking spell slots for one of your characters.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "track")]
+async fn track_slots(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[max_length = 100]
+	name: String,
+	#[autocomplete = "super::autocomplete_class"]
+	#[description = "Class"]
+	class: String,
+	#[description = "Class level"]
+	#[min = 1]
+	#[max = 20]
+	level: u8,
+	#[description = "Caster type override"] caster: Option<CasterType>,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let serenity::UserId(owner_id) = ctx.author().id;
+	let level = level.clamp(1, 20);
+
+	let tracker = if let Some(mut tracker) = find_tracker(&mut conn, ctx, Some(&name))? {
+		tracker.class = class;
+		tracker.level = level;
+		if let Some(caster) = caster {
+			tracker.caster = Some(caster.to_string());
+		}
+		diesel::update(&tracker).set(&tracker).execute(&mut *conn)?;
+		tracker
+	} else {
+		diesel::insert_into(SlotTrackers::table)
+			.values(&NewSlotTracker {
+				guild: guild_id,
+				owner: owner_id,
+				name: &name,
+				class: &class,
+				level,
+				caster: caster.map(|c| c.to_string()),
+			})
+			.execute(&mut *conn)?;
+		find_tracker(&mut conn, ctx, Some(&name))?.expect("Inserted tracker")
+	};
+
+	let embed = status_embed(&tracker, &tracker_progression(ctx, &tracker).await);
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// Spend a spell slot.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "use")]
+async fn use_slot(
+	ctx: Context<'_>,
+	#[description = "Slot level"]
+	#[min = 1]
+	#[max = 9]
+	level: u8,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_tracker"]
+	name: Option<String>,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let Some(mut tracker) = find_tracker(&mut conn, ctx, name.as_deref())? else {
+		ctx.say(NO_TRACKER).await?;
+		return Ok(());
+	};
+	let progression = tracker_progression(ctx, &tracker).await;
+
+	let i = usize::from(level.clamp(1, 9) - 1);
+	let max = progression.caster.slots(tracker.level);
+	let mut spent = tracker.spent_slots();
+
+	if spent[i] < max[i] {
+		spent[i] += 1;
+		tracker.set_spent_slots(spent);
+	} else if progression
+		.caster
+		.pact_slots(tracker.level)
+		.is_some_and(|(count, pact_level)| pact_level == level && tracker.pact_spent < count)
+	{
+		tracker.pact_spent += 1;
+	} else {
+		ctx.say(format!(
+			"{} has no {} level slots left.",
+			tracker.name,
+			ordinal(level)
+		))
+		.await?;
+		return Ok(());
+	}
+
+	let message = format!("{} used a {} level slot.", tracker.name, ordinal(level));
+	save_and_show(ctx, &mut conn, &tracker, message).await
+}
+
+/// Restore spent spell slots.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "restore")]
+async fn restore_slots(
+	ctx: Context<'_>,
+	#[description = "Slot level (all slots if omitted)"]
+	#[min = 1]
+	#[max = 9]
+	level: Option<u8>,
+	#[description = "Number of slots to restore"]
+	#[min = 1]
+	count: Option<u8>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_tracker"]
+	name: Option<String>,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let Some(mut tracker) = find_tracker(&mut conn, ctx, name.as_deref())? else {
+		ctx.say(NO_TRACKER).await?;
+		return Ok(());
+	};
+
+	let message = if let Some(level) = level {
+		let progression = tracker_progression(ctx, &tracker).await;
+		let i = usize::from(level.clamp(1, 9) - 1);
+		let count = count.unwrap_or(1);
+		let mut spent = tracker.spent_slots();
+
+		if spent[i] > 0 {
+			spent[i] = spent[i].saturating_sub(count);
+			tracker.set_spent_slots(spent);
+		} else if tracker.pact_spent > 0
+			&& progression
+				.caster
+				.pact_slots(tracker.level)
+				.is_some_and(|(_, pact_level)| pact_level == level)
+		{
+			tracker.pact_spent = tracker.pact_spent.saturating_sub(count);
+		} else {
+			ctx.say(format!(
+				"{} has no spent {} level slots to restore.",
+				tracker.name,
+				ordinal(level)
+			))
+			.await?;
+			return Ok(());
+		}
+		format!("Restored {} level slots.", ordinal(level))
+	} else {
+		if tracker.spent_slots() == [0; 9] && tracker.pact_spent == 0 {
+			ctx.say(format!("{} has no spent slots to restore.", tracker.name))
+				.await?;
+			return Ok(());
+		}
+		tracker.set_spent_slots([0; 9]);
+		tracker.pact_spent = 0;
+		"Restored all slots.".to_string()
+	};
+
+	save_and_show(ctx, &mut conn, &tracker, message).await
+}
+
+/// Show remaining spell slots.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "status")]
+async fn slot_status(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_tracker"]
+	name: Option<String>,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let Some(tracker) = find_tracker(&mut conn, ctx, name.as_deref())? else {
+		ctx.say(NO_TRACKER).await?;
+		return Ok(());
+	};
+
+	let embed = status_embed(&tracker, &tracker_progression(ctx, &tracker).await);
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+/// Stop tracking spell slots for one of your characters.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "delete")]
+async fn delete_tracker(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_tracker"]
+	name: String,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let Some(tracker) = find_tracker(&mut conn, ctx, Some(&name))? else {
+		ctx.say(NO_TRACKER).await?;
+		return Ok(());
+	};
+
+	diesel::delete(&tracker).execute(&mut *conn)?;
+	ctx.say(format!("Stopped tracking slots for {}.", tracker.name))
+		.await?;
+
+	Ok(())
+}
+
+/// Long rest: restore all spell slots and Arcane Recovery.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "long")]
+async fn long_rest(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_tracker"]
+	name: Option<String>,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let Some(mut tracker) = find_tracker(&mut conn, ctx, name.as_deref())? else {
+		ctx.say(NO_TRACKER).await?;
+		return Ok(());
+	};
+
+	tracker.set_spent_slots([0; 9]);
+	tracker.pact_spent = 0;
+	tracker.arcane_recovery = false;
+
+	let message = format!("{} finished a long rest.", tracker.name);
+	save_and_show(ctx, &mut conn, &tracker, message).await
+}
+
+/// Short rest: restore pact slots, and optionally use Arcane Recovery.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "short")]
+async fn short_rest(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_tracker"]
+	name: Option<String>,
+	#[description = "Use Arcane Recovery (Wizard)"]
+	#[flag]
+	arcane_recovery: bool,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let Some(mut tracker) = find_tracker(&mut conn, ctx, name.as_deref())? else {
+		ctx.say(NO_TRACKER).await?;
+		return Ok(());
+	};
+
+	tracker.pact_spent = 0;
+	let mut message = format!("{} finished a short rest.", tracker.name);
+
+	if arcane_recovery {
+		if !tracker.class.eq_ignore_ascii_case("wizard") {
+			message += " Only wizards have Arcane Recovery.";
+		} else if tracker.arcane_recovery {
+			message += " Arcane Recovery was already used since the last long rest.";
+		} else {
+			let recovered = recover_slots(&mut tracker);
+			tracker.arcane_recovery = true;
+			if recovered.is_empty() {
+				message += " No slots to recover.";
+			} else {
+				message += " Arcane Recovery restored: ";
+				message += &recovered.join(", ");
+			}
+		}
+	}
+
+	save_and_show(ctx, &mut conn, &tracker, message).await
+}
+
+/// Arcane Recovery: restores spent slots of up to 5th level, with a combined level of at most
+/// half the wizard level (rounded up). Highest slots are restored first.
+fn recover_slots(tracker: &mut SlotTracker) -> Vec<String> {
+	let mut budget = tracker.level.div_ceil(2);
+	let mut spent = tracker.spent_slots();
+	let mut recovered = Vec::new();
+
+	for level in (1..=5u8).rev() {
+		let i = usize::from(level - 1);
+		while spent[i] > 0 && level <= budget {
+			spent[i] -= 1;
+			budget -= level;
+			recovered.push(ordinal(level));
+		}
+	}
+
+	tracker.set_spent_slots(spent);
+	recovered
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn wizard(level: u8, spent: &str) -> SlotTracker {
+		SlotTracker {
+			id: 1,
+			guild: 1,
+			owner: 1,
+			name: "Wizard".to_string(),
+			class: "wizard".to_string(),
+			level,
+			caster: None,
+			spent: spent.to_string(),
+			pact_spent: 0,
+			arcane_recovery: false,
+		}
+	}
+
+	#[test]
+	fn recovers_highest_slots_first() {
+		let mut tracker = wizard(5, "2,1,1,0,0,0,0,0,0");
+		assert_eq!(recover_slots(&mut tracker), ["3rd"]);
+		assert_eq!(tracker.spent_slots(), [2, 1, 0, 0, 0, 0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn recovers_lower_slots_when_higher_ones_do_not_fit() {
+		let mut tracker = wizard(3, "1,0,1,0,0,0,0,0,0");
+		assert_eq!(recover_slots(&mut tracker), ["1st"]);
+		assert_eq!(tracker.spent_slots(), [0, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+		let mut tracker = wizard(6, "3,0,0,0,0,0,0,0,0");
+		assert_eq!(recover_slots(&mut tracker), ["1st", "1st", "1st"]);
+		assert_eq!(tracker.spent_slots(), [0; 9]);
+	}
+
+	#[test]
+	fn does_not_recover_slots_above_fifth_level() {
+		let mut tracker = wizard(20, "0,0,0,0,1,1,0,0,0");
+		assert_eq!(recover_slots(&mut tracker), ["5th"]);
+		assert_eq!(tracker.spent_slots(), [0, 0, 0, 0, 0, 1, 0, 0, 0]);
+	}
+
+	#[test]
+	fn recovers_nothing_without_spent_slots() {
+		let mut tracker = wizard(10, "0,0,0,0,0,0,0,0,0");
+		assert!(recover_slots(&mut tracker).is_empty());
+	}
+}
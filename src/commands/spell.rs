@@ -0,0 +1,200 @@
+use itertools::Itertools;
+use poise::serenity_prelude::CreateEmbed;
+
+use super::slots::ordinal;
+use crate::{data::Spell, Context, Error};
+
+/// Shortens a spell description to its first paragraph, cut at a word boundary.
+pub fn summary(description: &str, max_len: usize) -> String {
+	let first = description.split("\n\n").next().unwrap_or_default().trim();
+	if first.chars().count() <= max_len {
+		return first.to_string();
+	}
+
+	let mut cut: String = first.chars().take(max_len).collect();
+	if let Some(i) = cut.rfind(' ') {
+		cut.truncate(i);
+	}
+	cut + "…"
+}
+
+/// Embed field values are limited to 1024 characters.
+fn field_value(value: String) -> String {
+	const MAX_LEN: usize = 1024;
+	if value.chars().count() <= MAX_LEN {
+		return value;
+	}
+
+	let mut cut: String = value.chars().take(MAX_LEN - 1).collect();
+	cut.push('…');
+	cut
+}
+
+fn level_text(spell: &Spell) -> String {
+	if spell.level == 0 {
+		"Cantrip".to_string()
+	} else {
+		ordinal(spell.level)
+	}
+}
+
+fn yes_no(value: bool) -> String {
+	if value { "Yes" } else { "No" }.to_string()
+}
+
+type Attribute = (&'static str, fn(&Spell) -> String);
+
+const ATTRIBUTES: [Attribute; 10] = [
+	("Level", level_text),
+	("School", |spell| spell.school.name().to_string()),
+	("Casting time", |spell| spell.casting_time.clone()),
+	("Range", |spell| spell.range.clone()),
+	("Components", |spell| spell.components.clone()),
+	("Duration", |spell| spell.duration.clone()),
+	("Concentration", |spell| yes_no(spell.concentration)),
+	("Ritual", |spell| yes_no(spell.ritual)),
+	("Classes", |spell| spell.classes.iter().sorted().join(", ")),
+	("Source", |spell| spell.source.to_string()),
+];
+
+/// A spell's attributes and full description.
+pub fn spell_embed(spell: &Spell) -> CreateEmbed {
+	// Embed descriptions are limited to 4096 characters.
+	let mut description: String = spell.description.chars().take(4000).collect();
+	if description.len() < spell.description.len() {
+		description.push('…');
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed.title(&spell.name).description(description);
+	for (label, value) in ATTRIBUTES {
+		let value = value(spell);
+		if !value.is_empty() {
+			embed.field(label, field_value(value), true);
+		}
+	}
+	embed
+}
+
+/// Look up spells.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands("compare_spells")
+)]
+#[allow(clippy::unused_async)]
+pub async fn spell(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+/// Compare two or three spells side by side. Differences are shown in bold.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "compare")]
+async fn compare_spells(
+	ctx: Context<'_>,
+	#[description = "First spell"]
+	#[autocomplete = "super::autocomplete_spell"]
+	a: String,
+	#[description = "Second spell"]
+	#[autocomplete = "super::autocomplete_spell"]
+	b: String,
+	#[description = "Third spell"]
+	#[autocomplete = "super::autocomplete_spell"]
+	c: Option<String>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+
+	let embed = {
+		let spell_map_map = ctx.data().spell_map.read().await;
+		let spell_map = spell_map_map
+			.get(&guild_id)
+			.expect("Spell map not build for this guild yet.");
+
+		let names: Vec<String> = [Some(a), Some(b), c].into_iter().flatten().collect();
+		let missing: Vec<&String> = names
+			.iter()
+			.filter(|name| spell_map.get_spell(name).is_none())
+			.collect();
+		if !missing.is_empty() {
+			ctx.say(format!(
+				"Not available in this guild: {}",
+				missing.iter().join(", ")
+			))
+			.await?;
+			return Ok(());
+		}
+
+		let spells: Vec<&Spell> = names
+			.iter()
+			.filter_map(|name| spell_map.get_spell(name))
+			.collect();
+
+		let mut embed = CreateEmbed::default();
+		embed.title(spells.iter().map(|spell| &spell.name).join(" vs "));
+
+		let values: Vec<Vec<String>> = ATTRIBUTES
+			.iter()
+			.map(|(_, value)| spells.iter().map(|spell| value(spell)).collect())
+			.collect();
+
+		for (i, spell) in spells.iter().enumerate() {
+			let lines = ATTRIBUTES
+				.iter()
+				.zip(&values)
+				.filter(|(_, values)| !values[i].is_empty())
+				.map(|((label, _), values)| {
+					if values.iter().all_equal() {
+						format!("{label}: {}", values[i])
+					} else {
+						format!("{label}: **{}**", values[i])
+					}
+				})
+				.join("\n");
+
+			let summary = summary(&spell.description, 300);
+			embed.field(
+				&spell.name,
+				field_value(if summary.is_empty() {
+					lines
+				} else {
+					format!("{lines}\n\n{summary}")
+				}),
+				true,
+			);
+		}
+		embed
+	};
+
+	ctx.send(|m| {
+		m.embeds.push(embed);
+		m
+	})
+	.await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn summary_keeps_the_first_paragraph() {
+		assert_eq!(summary("Short.\n\nMore text.", 300), "Short.");
+	}
+
+	#[test]
+	fn summary_cuts_at_a_word_boundary() {
+		assert_eq!(summary("A bright streak flashes", 12), "A bright…");
+	}
+
+	#[test]
+	fn field_value_fits_the_field_limit() {
+		let short = "x".repeat(1024);
+		assert_eq!(field_value(short.clone()), short);
+
+		let long = field_value("é".repeat(2000));
+		assert_eq!(long.chars().count(), 1024);
+		assert!(long.ends_with('…'));
+	}
+}
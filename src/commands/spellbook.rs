@@ -0,0 +1,284 @@
+use diesel::prelude::*;
+use futures::{Stream, StreamExt};
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+
+use super::{spells::level_lines, Line};
+use crate::{
+	models::*,
+	schema::{SpellbookSpells, Spellbooks},
+	Context, Error,
+};
+
+/// Manage your characters' spellbooks.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands(
+		"create_spellbook",
+		"add_spell",
+		"remove_spell",
+		"show_spellbook",
+		"delete_spellbook"
+	)
+)]
+#[allow(clippy::unused_async)]
+pub async fn spellbook(_ctx: Context<'_>) -> Result<(), Error> {
+	Ok(())
+}
+
+async fn autocomplete_spellbook<'a>(
+	ctx: Context<'_>,
+	partial: &'a str,
+) -> impl Stream<Item = String> + 'a {
+	let mut conn = ctx.data().db.lock().await;
+	let serenity::GuildId(guild_id) = ctx.guild_id().unwrap_or_default();
+	let serenity::UserId(owner_id) = ctx.author().id;
+
+	let names = Spellbooks::table
+		.filter(Spellbooks::guild.eq(guild_id))
+		.filter(Spellbooks::owner.eq(owner_id))
+		.select(Spellbooks::name)
+		.load::<String>(&mut *conn)
+		.unwrap_or_default();
+
+	futures::stream::iter(names).filter(move |name| {
+		futures::future::ready(name.to_lowercase().starts_with(&partial.to_lowercase()))
+	})
+}
+
+fn find_spellbook(
+	conn: &mut MysqlConnection,
+	ctx: Context<'_>,
+	name: &str,
+) -> QueryResult<Option<Spellbook>> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let serenity::UserId(owner_id) = ctx.author().id;
+
+	Spellbooks::table
+		.filter(Spellbooks::guild.eq(guild_id))
+		.filter(Spellbooks::owner.eq(owner_id))
+		.filter(Spellbooks::name.eq(name))
+		.select((Spellbooks::id, Spellbooks::name))
+		.first::<Spellbook>(conn)
+		.optional()
+}
+
+/// Create a spellbook for one of your characters.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "create")]
+async fn create_spellbook(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[max_length = 100]
+	name: String,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let serenity::UserId(owner_id) = ctx.author().id;
+
+	if find_spellbook(&mut conn, ctx, &name)?.is_some() {
+		ctx.say(format!("You already have a spellbook named {name}."))
+			.await?;
+		return Ok(());
+	}
+
+	let book = NewSpellbook {
+		guild: guild_id,
+		owner: owner_id,
+		name: &name,
+	};
+
+	diesel::insert_into(Spellbooks::table)
+		.values(&book)
+		.execute(&mut *conn)?;
+
+	ctx.say(format!("Created spellbook: {name}")).await?;
+
+	Ok(())
+}
+
+/// Add a spell to one of your spellbooks.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "add")]
+async fn add_spell(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_spellbook"]
+	name: String,
+	#[description = "Spell to add"]
+	#[autocomplete = "super::autocomplete_spell"]
+	#[rest]
+	spell: String,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+
+	let spell_name = {
+		let spell_map = ctx.data().spell_map.read().await;
+		spell_map
+			.get(&guild_id)
+			.and_then(|sm| sm.get_spell(&spell))
+			.map(|spell| spell.name.clone())
+	};
+	let Some(spell_name) = spell_name else {
+		ctx.say(format!("{spell} is not available in this guild."))
+			.await?;
+		return Ok(());
+	};
+
+	let mut conn = ctx.data().db.lock().await;
+	let Some(book) = find_spellbook(&mut conn, ctx, &name)? else {
+		ctx.say(format!("You don't have a spellbook named {name}."))
+			.await?;
+		return Ok(());
+	};
+
+	let count = SpellbookSpells::table
+		.filter(SpellbookSpells::spellbook.eq(book.id))
+		.filter(SpellbookSpells::spell.eq(&spell_name))
+		.count()
+		.get_result::<i64>(&mut *conn)?;
+
+	if count == 0 {
+		diesel::insert_into(SpellbookSpells::table)
+			.values(&NewSpellbookSpell {
+				spellbook: book.id,
+				spell: &spell_name,
+			})
+			.execute(&mut *conn)?;
+
+		ctx.say(format!("Added {spell_name} to {}.", book.name))
+			.await?;
+	} else {
+		ctx.say(format!("{spell_name} is already in {}.", book.name))
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Remove a spell from one of your spellbooks.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "remove")]
+async fn remove_spell(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_spellbook"]
+	name: String,
+	#[description = "Spell to remove"]
+	#[autocomplete = "super::autocomplete_spell"]
+	#[rest]
+	spell: String,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+
+	// Spells that left the guild's tomes can still be removed by the name they were added under.
+	let spell = {
+		let spell_map = ctx.data().spell_map.read().await;
+		spell_map
+			.get(&guild_id)
+			.and_then(|sm| sm.get_spell(&spell))
+			.map_or(spell, |spell| spell.name.clone())
+	};
+
+	let mut conn = ctx.data().db.lock().await;
+	let Some(book) = find_spellbook(&mut conn, ctx, &name)? else {
+		ctx.say(format!("You don't have a spellbook named {name}."))
+			.await?;
+		return Ok(());
+	};
+
+	let count = diesel::delete(
+		SpellbookSpells::table
+			.filter(SpellbookSpells::spellbook.eq(book.id))
+			.filter(SpellbookSpells::spell.eq(&spell)),
+	)
+	.execute(&mut *conn)?;
+
+	if count > 0 {
+		ctx.say(format!("Removed {spell} from {}.", book.name))
+			.await?;
+	} else {
+		ctx.say(format!("{spell} is not in {}.", book.name)).await?;
+	}
+
+	Ok(())
+}
+
+/// Show the spells in one of your spellbooks.
+#[poise::command(prefix_command, slash_command, guild_only, ephemeral, rename = "show")]
+async fn show_spellbook(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_spellbook"]
+	name: String,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().expect("Guild Id");
+
+	let (book, spells) = {
+		let mut conn = ctx.data().db.lock().await;
+		let Some(book) = find_spellbook(&mut conn, ctx, &name)? else {
+			ctx.say(format!("You don't have a spellbook named {name}."))
+				.await?;
+			return Ok(());
+		};
+
+		let spells = SpellbookSpells::table
+			.filter(SpellbookSpells::spellbook.eq(book.id))
+			.select(SpellbookSpells::spell)
+			.load::<String>(&mut *conn)?;
+
+		(book, spells)
+	};
+
+	if spells.is_empty() {
+		ctx.say(format!("{} is empty.", book.name)).await?;
+		return Ok(());
+	}
+
+	let spell_map_map = ctx.data().spell_map.read().await;
+	let spell_map = spell_map_map
+		.get(&guild_id)
+		.expect("Spell map not build for this guild yet.");
+
+	let (known, unknown): (Vec<_>, Vec<_>) = spells
+		.iter()
+		.map(|name| (name, spell_map.get_spell(name)))
+		.partition(|(_, spell)| spell.is_some());
+
+	let mut lines = level_lines(known.into_iter().filter_map(|(_, spell)| spell));
+	if !unknown.is_empty() {
+		lines.push(Line::header("**No longer available**".to_string()));
+		lines.extend(unknown.into_iter().map(|(name, _)| name.clone().into()));
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed.title(&book.name);
+
+	super::send_paginated_message(ctx, lines, embed, false).await?;
+
+	Ok(())
+}
+
+/// Delete one of your spellbooks.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "delete")]
+async fn delete_spellbook(
+	ctx: Context<'_>,
+	#[description = "Character name"]
+	#[autocomplete = "autocomplete_spellbook"]
+	name: String,
+) -> Result<(), Error> {
+	let mut conn = ctx.data().db.lock().await;
+	let Some(book) = find_spellbook(&mut conn, ctx, &name)? else {
+		ctx.say(format!("You don't have a spellbook named {name}."))
+			.await?;
+		return Ok(());
+	};
+
+	conn.transaction(|conn| {
+		diesel::delete(SpellbookSpells::table.filter(SpellbookSpells::spellbook.eq(book.id)))
+			.execute(conn)?;
+		diesel::delete(Spellbooks::table.find(book.id)).execute(conn)
+	})?;
+
+	ctx.say(format!("Deleted spellbook: {}", book.name)).await?;
+
+	Ok(())
+}
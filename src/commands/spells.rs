@@ -1,22 +1,37 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::Arc,
+};
 
 use anyhow::anyhow;
 use diesel::prelude::*;
 use futures::{stream::FuturesUnordered, StreamExt};
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use poise::serenity_prelude::{self as serenity, CreateEmbed, GuildId, Typing};
+use poise::serenity_prelude::{self as serenity, GuildId, Typing};
+use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, RwLock};
 
+use super::{tomes::UPLOAD_PREFIX, Line};
 use crate::{
-	data::{sources, Spell, SpellCollection, SpellSchool},
+	data::{
+		progression::{default_progressions, Progression},
+		sources, DuplicatePolicy, Edition, Spell, SpellCollection, SpellOrigin, SpellSchool,
+		SpellSource,
+	},
 	models::GuildTome,
+	schema::UploadedTomes,
 	Context, Error,
 };
 
 /// Lists spells for specified class and level (prefix command)
 ///
-#[poise::command(prefix_command, ephemeral, rename = "sl")]
+/// Additional arguments: `--ritual`, `--concentration`, `--<school>`, \
+/// `!<class>` to exclude a class's spells, \
+/// `from:<book or tome>` and `!from:<book or tome>` to include or exclude a source, \
+/// `--homebrew-only` or `--official-only`, `sort:<name|level|school>`, \
+/// `group:<level|school|source|class>`, and `--public` to post the list for everyone.
+#[poise::command(prefix_command, rename = "sl")]
 pub async fn spell_list_prefix(
 	ctx: Context<'_>,
 	#[description = "Class"] class: String,
@@ -25,29 +40,13 @@ pub async fn spell_list_prefix(
 	#[description = "Additional arguments"]
 	args: Option<String>,
 ) -> Result<(), Error> {
-	let (min_level, max_level) = if let Some(level) = level {
-		if level.contains('-') {
-			let mut spl = level.split('-');
-
-			let (one, two): (Option<u8>, Option<u8>) = (
-				spl.next().and_then(|el| el.parse().ok()),
-				spl.next().and_then(|el| el.parse().ok()),
-			);
-
-			(one, two)
-		} else {
-			let p: Option<u8> = level.parse().ok();
-
-			(p, p)
-		}
-	} else {
-		(None, None)
-	};
+	let (min_level, max_level) = level.as_deref().map_or((None, None), parse_levels);
 
 	if let Some(args) = args {
 		let mut args = args.split(' ');
 		// log::info!("Spell List (Prefix): {ctx:?}");
 		let ritual = args.clone().any(|el| el.contains("--ritual"));
+		let concentration = args.clone().any(|el| el.contains("--concentration"));
 
 		let spell_schools: Vec<_> = args
 			.clone()
@@ -59,8 +58,44 @@ pub async fn spell_list_prefix(
 			})
 			.collect();
 
+		let sources: Vec<String> = args
+			.clone()
+			.filter_map(|arg| arg.strip_prefix(SOURCE_ARG))
+			.map(String::from)
+			.collect();
+
+		let not_sources: Vec<String> = args
+			.clone()
+			.filter_map(|arg| arg.strip_prefix('!')?.strip_prefix(SOURCE_ARG))
+			.map(String::from)
+			.collect();
+
+		let origin = if args.clone().any(|arg| arg == "--homebrew-only") {
+			Some(SpellOrigin::Homebrew)
+		} else if args.clone().any(|arg| arg == "--official-only") {
+			Some(SpellOrigin::Official)
+		} else {
+			None
+		};
+
+		let sort = args
+			.clone()
+			.find_map(|arg| arg.strip_prefix(SORT_ARG)?.parse().ok())
+			.unwrap_or_default();
+		let group_by = args
+			.clone()
+			.find_map(|arg| arg.strip_prefix(GROUP_ARG)?.parse().ok())
+			.unwrap_or_default();
+
+		let public = args.clone().any(|arg| arg == "--public").then_some(true);
+
 		let not_classes: Vec<String> = args
 			.clone()
+			.filter(|arg| {
+				![SOURCE_ARG, "!from:", SORT_ARG, GROUP_ARG]
+					.iter()
+					.any(|prefix| arg.starts_with(prefix))
+			})
 			.filter_map(|arg| {
 				arg.split('!')
 					.last()
@@ -77,22 +112,65 @@ pub async fn spell_list_prefix(
 
 		spell_list(
 			ctx,
-			class,
-			min_level,
-			max_level,
-			ritual,
-			spell_schools,
-			not_classes,
+			SpellFilter {
+				class: Some(class),
+				min_level,
+				max_level,
+				ritual,
+				concentration,
+				schools: spell_schools,
+				not_classes,
+				sources,
+				not_sources,
+				origin,
+				sort,
+				group_by,
+			},
+			public,
 		)
 		.await
 	} else {
-		spell_list(ctx, class, min_level, max_level, false, vec![], vec![]).await
+		spell_list(
+			ctx,
+			SpellFilter {
+				class: Some(class),
+				min_level,
+				max_level,
+				..Default::default()
+			},
+			None,
+		)
+		.await
+	}
+}
+
+/// Reads a level like `3`, or a range like `1-3`, from the prefix command.
+fn parse_levels(level: &str) -> (Option<u8>, Option<u8>) {
+	if level.contains('-') {
+		let mut spl = level.split('-');
+
+		let (one, two): (Option<u8>, Option<u8>) = (
+			spl.next().and_then(|el| el.parse().ok()),
+			spl.next().and_then(|el| el.parse().ok()),
+		);
+
+		(one, two)
+	} else {
+		let p: Option<u8> = level.parse().ok();
+
+		(p, p)
 	}
 }
 
+/// Prefix of the source filter in the prefix command's arguments.
+const SOURCE_ARG: &str = "from:";
+/// Prefixes of the sort and group options in the prefix command's arguments.
+const SORT_ARG: &str = "sort:";
+const GROUP_ARG: &str = "group:";
+
 /// Lists spells for specified class and level (slash command)
 #[allow(clippy::too_many_arguments)]
-#[poise::command(slash_command, ephemeral, rename = "spells")]
+#[poise::command(slash_command, rename = "spells")]
 pub async fn spell_list_slash(
 	ctx: Context<'_>,
 	#[autocomplete = "super::autocomplete_class"]
@@ -103,10 +181,10 @@ pub async fn spell_list_slash(
 	level: Option<u8>,
 	#[autocomplete = "super::autocomplete_level"]
 	#[description = "Minimum spell level"]
-	mut min_level: Option<u8>,
+	min_level: Option<u8>,
 	#[autocomplete = "super::autocomplete_level"]
 	#[description = "Maximum spell level"]
-	mut max_level: Option<u8>,
+	max_level: Option<u8>,
 	#[description = "Filter spell schools"]
 	#[autocomplete = "super::autocomplete_school"]
 	// spell_schools: Vec<SpellSchool>,
@@ -114,123 +192,238 @@ pub async fn spell_list_slash(
 	#[description = "Only display ritual spells"]
 	#[flag]
 	ritual: bool,
+	#[description = "Only display concentration spells"]
+	#[flag]
+	concentration: bool,
 	#[autocomplete = "super::autocomplete_class"]
 	#[description = "Exclude spells which belong to this class's spell list"]
 	not_classes: Vec<String>,
+	#[autocomplete = "super::autocomplete_source"]
+	#[description = "Only show spells from these books or tomes"]
+	sources: Vec<String>,
+	#[autocomplete = "super::autocomplete_source"]
+	#[description = "Hide spells from these books or tomes"]
+	not_sources: Vec<String>,
+	#[description = "Only show official or homebrew spells"] origin: Option<SpellOrigin>,
+	#[description = "Order spells within each group by name, level or school"] sort: Option<SortBy>,
+	#[description = "Group spells by level, school, source or class"] group_by: Option<GroupBy>,
+	#[description = "Post the list to the channel for everyone"] public: Option<bool>,
 	// #[rest]
 	// #[description = "Additional arguments"]
 	// args: Option<String>,
 ) -> Result<(), Error> {
-	if min_level.is_some() && max_level.is_none() {
-		max_level = Some(9);
-	} else if max_level.is_some() && min_level.is_none() {
-		min_level = Some(0);
-	} else if level.is_some() {
-		min_level = Some(level.unwrap());
-		max_level = Some(level.unwrap());
-	}
+	let (min_level, max_level) = level_range(level, min_level, max_level);
 
 	spell_list(
 		ctx,
-		class,
-		min_level,
-		max_level,
-		ritual,
-		if let Some(spell_school) = spell_school {
-			vec![spell_school]
-		} else {
-			Vec::new()
+		SpellFilter {
+			class: Some(class),
+			min_level,
+			max_level,
+			ritual,
+			concentration,
+			schools: spell_school.into_iter().collect(),
+			not_classes,
+			sources,
+			not_sources,
+			origin,
+			sort: sort.unwrap_or_default(),
+			group_by: group_by.unwrap_or_default(),
 		},
-		not_classes,
+		public,
 	)
 	.await
 }
 
-async fn spell_list(
-	ctx: Context<'_>,
-	class: String,
-	// level: Option<String>,
+/// Resolves the `level`, `min_level` and `max_level` options of a slash command into a range.
+pub fn level_range(
+	level: Option<u8>,
 	min_level: Option<u8>,
 	max_level: Option<u8>,
-	ritual: bool,
-	spell_schools: Vec<SpellSchool>,
-	not_classes: Vec<String>,
-) -> Result<(), Error> {
-	ctx.defer_ephemeral().await?;
-	let guild_id = ctx.guild_id().unwrap();
+) -> (Option<u8>, Option<u8>) {
+	match (level, min_level, max_level) {
+		(_, Some(min), None) => (Some(min), Some(9)),
+		(_, None, Some(max)) => (Some(0), Some(max)),
+		(Some(level), None, None) => (Some(level), Some(level)),
+		(_, min, max) => (min, max),
+	}
+}
 
-	let spell_map_map = ctx.data().spell_map.read().await;
-	let spell_map = spell_map_map
-		.get(&guild_id)
-		.expect("Spell map not build for this guild yet.");
+/// What spell lists are split into groups by.
+#[derive(
+	Debug, Clone, Copy, Default, Serialize, Deserialize, poise::ChoiceParameter, PartialEq, Eq,
+)]
+pub enum GroupBy {
+	#[default]
+	Level,
+	School,
+	Source,
+	Class,
+}
 
-	let iter = spell_map
-		.get_spells(&class.to_lowercase())
-		.unwrap()
-		.into_iter()
-		.filter(|el| !ritual || el.ritual)
-		.filter(|el| {
-			if let Some(min_level) = min_level {
-				el.level >= min_level
-			} else {
-				true
-			}
-		})
-		.filter(|el| {
-			if let Some(max_level) = max_level {
-				el.level <= max_level
-			} else {
-				true
-			}
-		})
-		.filter(|el| {
-			if let Some(max_level) = max_level {
-				el.level <= max_level
-			} else {
-				true
+/// How spells are ordered within each group of a spell list, by name after that.
+#[derive(
+	Debug, Clone, Copy, Default, Serialize, Deserialize, poise::ChoiceParameter, PartialEq, Eq,
+)]
+pub enum SortBy {
+	#[default]
+	Name,
+	Level,
+	School,
+}
+
+impl SortBy {
+	fn sort(self, spells: &mut [&Spell]) {
+		spells.sort_unstable_by(|a, b| {
+			match self {
+				Self::Name => std::cmp::Ordering::Equal,
+				Self::Level => a.level.cmp(&b.level),
+				Self::School => a.school.name().cmp(b.school.name()),
 			}
-		})
-		.filter(|spell| {
-			not_classes.is_empty()
-				|| !spell
-					.classes
+			.then_with(|| a.name.cmp(&b.name))
+		});
+	}
+}
+
+/// Filters shared by the spell listing commands, and how to order the list. Spell lists keep
+/// theirs so the filter controls on the message can change it later.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpellFilter {
+	pub class: Option<String>,
+	pub min_level: Option<u8>,
+	pub max_level: Option<u8>,
+	pub ritual: bool,
+	pub concentration: bool,
+	pub schools: Vec<SpellSchool>,
+	pub not_classes: Vec<String>,
+	/// Books or tomes, see [`SpellSource::is`].
+	pub sources: Vec<String>,
+	pub not_sources: Vec<String>,
+	pub origin: Option<SpellOrigin>,
+	pub sort: SortBy,
+	pub group_by: GroupBy,
+}
+
+impl SpellFilter {
+	pub fn matches(&self, spell: &Spell) -> bool {
+		(!self.ritual || spell.ritual)
+			&& (!self.concentration || spell.concentration)
+			&& self.min_level.is_none_or(|min| spell.level >= min)
+			&& self.max_level.is_none_or(|max| spell.level <= max)
+			&& (self.schools.is_empty() || self.schools.contains(&spell.school))
+			&& !spell.classes.iter().any(|class| {
+				self.not_classes
 					.iter()
-					.any(|class| not_classes.contains(class))
-		})
-		.filter(|spell| spell_schools.is_empty() || spell_schools.contains(&spell.school));
+					.any(|not_class| not_class.eq_ignore_ascii_case(class))
+			}) && self.matches_source(&spell.source)
+	}
+
+	fn matches_source(&self, source: &SpellSource) -> bool {
+		(self.sources.is_empty() || self.sources.iter().any(|name| source.is(name)))
+			&& !self.not_sources.iter().any(|name| source.is(name))
+			&& self.origin.is_none_or(|origin| origin.matches(source))
+	}
+
+	/// Spells in the map matching this filter, using the class index when a class is given.
+	pub fn apply<'a>(&self, spell_map: &'a SpellMap) -> Vec<&'a Spell> {
+		let spells = match &self.class {
+			Some(class) => spell_map
+				.get_spells(&class.to_lowercase())
+				.unwrap_or_default(),
+			None => spell_map.get_all_spells().iter().collect(),
+		};
 
-	let list: Vec<String> = if min_level.is_some() && min_level.eq(&max_level) {
-		iter.map(|el| &el.name)
-			.cloned()
-			.sorted_unstable()
-			.chunks(20)
+		spells
 			.into_iter()
-			.map(|mut c| c.join("\n"))
+			.filter(|spell| self.matches(spell))
 			.collect()
+	}
+}
+
+/// Lists the spells matching a filter. Unless `public` is given, the guild's default decides
+/// whether the list is posted for everyone.
+async fn spell_list(
+	ctx: Context<'_>,
+	filter: SpellFilter,
+	public: Option<bool>,
+) -> Result<(), Error> {
+	let guild_id = ctx.guild_id().unwrap();
+	let public = if let Some(public) = public {
+		public
 	} else {
-		iter.sorted_unstable_by_key(|el| el.level)
-			.group_by(|el| el.level)
-			.into_iter()
-			.flat_map(|(level, group)| {
-				std::iter::once(format!("**Level {level} spells**"))
-					.chain(group.map(|el| &el.name).cloned().sorted_unstable())
-			})
-			.chunks(20)
-			.into_iter()
-			.map(|mut c| c.join("\n"))
-			.collect()
+		super::settings::guild_settings(ctx.data(), guild_id)
+			.await
+			.public_lists
+	};
+	if public {
+		ctx.defer().await?;
+	} else {
+		ctx.defer_ephemeral().await?;
+	}
+
+	let list = {
+		let spell_map_map = ctx.data().spell_map.read().await;
+		let spell_map = spell_map_map
+			.get(&guild_id)
+			.expect("Spell map not build for this guild yet.");
+		list_lines(spell_map, &filter)
 	};
 
-	super::send_paginated_message(ctx, list, CreateEmbed::default()).await?;
+	super::send_spell_list(ctx, list, &filter, public).await?;
 
 	Ok(())
 }
 
+/// The lines of a spell list, grouped and sorted as the filter asks.
+pub fn list_lines(spell_map: &SpellMap, filter: &SpellFilter) -> Vec<Line> {
+	grouped_lines(filter.apply(spell_map), filter.group_by, filter.sort)
+}
+
+/// Lists spell names, grouped under a header for each spell level.
+pub fn level_lines<'a>(spells: impl Iterator<Item = &'a Spell>) -> Vec<Line> {
+	grouped_lines(spells.collect(), GroupBy::Level, SortBy::Name)
+}
+
+/// Lists spell names under a header for each group, with the number of spells in it. Spells of
+/// several classes show up in each of their classes' groups.
+fn grouped_lines(spells: Vec<&Spell>, group_by: GroupBy, sort: SortBy) -> Vec<Line> {
+	// Groups are ordered by the number first, which is only used to keep levels in order.
+	let mut groups: BTreeMap<(u8, String), Vec<&Spell>> = BTreeMap::new();
+	for spell in spells {
+		let keys = match group_by {
+			GroupBy::Level => vec![(spell.level, format!("Level {} spells", spell.level))],
+			GroupBy::School => vec![(0, spell.school.name().to_string())],
+			GroupBy::Source => vec![(0, spell.source.book.clone())],
+			GroupBy::Class if spell.classes.is_empty() => vec![(0, "No class".to_string())],
+			GroupBy::Class => spell
+				.classes
+				.iter()
+				.unique()
+				.map(|class| (0, class.clone()))
+				.collect(),
+		};
+		for key in keys {
+			groups.entry(key).or_default().push(spell);
+		}
+	}
+
+	groups
+		.into_iter()
+		.flat_map(|((_, name), mut group)| {
+			sort.sort(&mut group);
+			std::iter::once(Line::header(format!("**{name}** ({})", group.len())))
+				.chain(group.into_iter().map(Line::from))
+		})
+		.collect()
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SpellMap {
 	spells: Vec<Spell>,
 	classes: Vec<String>,
 	map: HashMap<String, Vec<usize>>,
+	progressions: HashMap<String, Progression>,
 }
 
 impl SpellMap {
@@ -267,67 +460,139 @@ impl SpellMap {
 	pub fn get_classes(&self) -> &Vec<String> {
 		&self.classes
 	}
+
+	pub fn get_all_spells(&self) -> &Vec<Spell> {
+		&self.spells
+	}
+
+	pub fn get_progression(&self, class: &str) -> Option<&Progression> {
+		self.progressions.get(&class.to_lowercase())
+	}
+
+	pub fn get_spell(&self, name: &str) -> Option<&Spell> {
+		self.spells
+			.iter()
+			.find(|spell| spell.name.eq_ignore_ascii_case(name))
+	}
 }
 
-pub async fn build_spell_map(guild_id: GuildId, conn: Arc<Mutex<MysqlConnection>>) -> SpellMap {
+/// The guild's own tomes, plus the SRD and every published 5etools book, and the Unearthed
+/// Arcana documents if the guild wants them.
+async fn guild_tomes(db: &Mutex<MysqlConnection>, gid: u64, include_ua: bool) -> Vec<GuildTome> {
 	use crate::schema::GuildTomes::dsl::*;
 
-	let mut conn = conn.lock().await;
-	let serenity::GuildId(gid) = guild_id;
-
 	let mut tomes = GuildTomes
 		.filter(guild.eq(gid))
-		.load::<GuildTome>(&mut *conn)
+		.load::<GuildTome>(&mut *db.lock().await)
 		.expect("Error loading guild tomes.");
 
 	tomes.push(GuildTome {
 		id: 0,
 		guild: 0,
 		source: "srd".to_string(),
+		priority: 0,
+		policy: DuplicatePolicy::Merge.as_str().to_string(),
 	});
 
 	tomes.extend(
 		crate::data::sources::get_5e_index()
 			.await
 			.keys()
-			.filter(|k| !k.starts_with("UA"))
+			.filter(|k| include_ua || !k.starts_with("UA"))
 			.map(|key| GuildTome {
 				id: 0,
 				guild: 0,
 				source: key.clone(),
+				priority: 0,
+				policy: DuplicatePolicy::Merge.as_str().to_string(),
 			}),
 	);
 
+	tomes
+}
+
+/// The database is only locked while reading and writing it, not while fetching tomes.
+pub async fn build_spell_map(guild_id: GuildId, db: Arc<Mutex<MysqlConnection>>) -> SpellMap {
+	let serenity::GuildId(gid) = guild_id;
+
+	let settings = super::settings::load_settings(&mut *db.lock().await, gid);
+	let tomes = guild_tomes(&db, gid, settings.include_ua).await;
+	let edition = settings.edition();
+
+	let uploads: HashMap<String, (String, String)> = UploadedTomes::table
+		.filter(UploadedTomes::guild.eq(gid))
+		.select((
+			UploadedTomes::name,
+			(UploadedTomes::format, UploadedTomes::content),
+		))
+		.load(&mut *db.lock().await)
+		.unwrap_or_else(|err| {
+			log::error!("Error loading uploaded tomes: {err}");
+			Vec::new()
+		})
+		.into_iter()
+		.collect();
+
 	let mut sm = SpellMap::default();
-	let mut spells_futures: FuturesUnordered<_> = tomes.iter().map(get_spells).collect();
+	let mut spells_futures: FuturesUnordered<_> = tomes
+		.iter()
+		.map(|tome| {
+			let uploads = &uploads;
+			async move { (tome, get_spells(tome, uploads).await) }
+		})
+		.collect();
 
-	let mut tomes: Vec<SpellCollection> = Vec::new();
-	while let Some(res) = spells_futures.next().await {
+	let mut tomes: Vec<(TomeRank, DuplicatePolicy, SpellCollection)> = Vec::new();
+	let mut snapshots = Vec::new();
+	while let Some((tome, res)) = spells_futures.next().await {
+		let src = &tome.source;
 		match res {
-			Ok(collection) => tomes.push(collection),
+			Ok(mut collection) => {
+				collection.attribute_spells();
+				// Only the guild's own tomes are tracked, including re-uploaded ones.
+				if tome.guild != 0 {
+					match super::tomes::snapshot(&collection) {
+						Ok(snapshot) => snapshots.push((src, snapshot)),
+						Err(err) => log::error!("Error serializing {src}: {err}"),
+					}
+				}
+				let duplicates = tome.policy.parse().unwrap_or_default();
+				tomes.push((TomeRank::of(tome), duplicates, collection));
+			}
 			Err(err) => log::error!("Error getting spell source: {err}"),
 		}
 	}
 
-	let spell_lists: HashMap<String, Vec<String>> =
-		tomes.iter().flat_map(|e| e.spell_lists.clone()).collect();
+	let mut conn = db.lock().await;
+	for (src, (content, hash)) in snapshots {
+		if let Err(err) = super::tomes::save_snapshot(&mut conn, gid, src, &content, hash) {
+			log::error!("Error saving snapshot of {src}: {err}");
+		}
+	}
 
-	tomes
+	// Guild homebrew always wins over spells of the same name from any tome.
+	match super::homebrew::homebrew_collection(&mut conn, gid) {
+		Ok(mut collection) => {
+			collection.attribute_spells();
+			tomes.push((TomeRank::Homebrew, DuplicatePolicy::Replace, collection));
+		}
+		Err(err) => log::error!("Error loading homebrew spells: {err}"),
+	}
+
+	tomes.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+
+	sm.progressions = default_progressions();
+	sm.progressions
+		.extend(tomes.iter().rev().flat_map(|(.., e)| {
+			e.progressions
+				.iter()
+				.map(|(class, progression)| (class.to_lowercase(), progression.clone()))
+		}));
+
+	merge_duplicates(tomes, edition)
 		.into_iter()
-		.flat_map(|e| e.spells)
 		.sorted_by(|a, b| a.name.cmp(&b.name))
-		.dedup_by(|a, b| a.name.eq(&b.name))
 		.for_each(|mut spell| {
-			spell
-				.classes
-				.extend(spell_lists.iter().filter_map(|(k, v)| {
-					if v.contains(&spell.name) {
-						Some(k.clone())
-					} else {
-						None
-					}
-				}));
-
 			for class in &mut spell.classes {
 				match class.as_str() {
 					"Artificier" => *class = String::from("Artificer"),
@@ -344,6 +609,92 @@ pub async fn build_spell_map(guild_id: GuildId, conn: Arc<Mutex<MysqlConnection>
 	sm
 }
 
+/// Where a tome's spells rank when several tomes have a spell with the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TomeRank {
+	Srd,
+	Book,
+	Guild(i32),
+	Homebrew,
+}
+
+impl TomeRank {
+	fn of(tome: &GuildTome) -> Self {
+		if tome.guild != 0 {
+			Self::Guild(tome.priority)
+		} else if tome.source == "srd" {
+			Self::Srd
+		} else {
+			Self::Book
+		}
+	}
+}
+
+/// Adds a class to a spell, unless it already has it.
+fn add_class(spell: &mut Spell, class: String) {
+	if !spell.classes.iter().any(|c| c.eq_ignore_ascii_case(&class)) {
+		spell.classes.push(class);
+	}
+}
+
+/// Keeps one copy of each spell, from the highest ranked tome. Tomes must be sorted by rank,
+/// highest first. Between equally ranked copies, the one from the guild's preferred edition wins.
+/// Depending on the winning tome's policy, the classes of the other copies are merged in or
+/// dropped. The same goes for the classes given by spell lists: a spell won by a tome that
+/// replaces duplicates only takes them from that tome's own lists.
+fn merge_duplicates(
+	tomes: Vec<(TomeRank, DuplicatePolicy, SpellCollection)>,
+	edition: Edition,
+) -> Vec<Spell> {
+	// Spells along with the rank, policy and position of the tome they were kept from.
+	let mut spells: Vec<(TomeRank, DuplicatePolicy, usize, Spell)> = Vec::new();
+	let mut index: HashMap<String, usize> = HashMap::new();
+	let mut spell_lists = Vec::new();
+
+	for (tome, (rank, policy, collection)) in tomes.into_iter().enumerate() {
+		spell_lists.push(collection.spell_lists);
+		for mut spell in collection.spells {
+			let key = spell.name.to_lowercase();
+			let Some(&i) = index.get(&key) else {
+				index.insert(key, spells.len());
+				spells.push((rank, policy, tome, spell));
+				continue;
+			};
+
+			let (kept_rank, kept_policy, kept_tome, kept) = &mut spells[i];
+			if *kept_rank == rank
+				&& spell.source.edition == Some(edition)
+				&& kept.source.edition != Some(edition)
+			{
+				std::mem::swap(kept, &mut spell);
+				*kept_policy = policy;
+				*kept_tome = tome;
+			}
+			if *kept_policy == DuplicatePolicy::Merge {
+				for class in spell.classes {
+					add_class(kept, class);
+				}
+			}
+		}
+	}
+
+	for (tome, lists) in spell_lists.into_iter().enumerate() {
+		for (class, names) in lists {
+			for name in names {
+				let Some(&i) = index.get(&name.to_lowercase()) else {
+					continue;
+				};
+				let (_, policy, kept_tome, spell) = &mut spells[i];
+				if *policy == DuplicatePolicy::Merge || *kept_tome == tome {
+					add_class(spell, class.clone());
+				}
+			}
+		}
+	}
+
+	spells.into_iter().map(|(.., spell)| spell).collect()
+}
+
 #[allow(clippy::too_many_arguments)]
 #[poise::command(slash_command, prefix_command, ephemeral, check = "super::is_manager")]
 pub async fn rebuild(ctx: Context<'_>) -> Result<(), Error> {
@@ -362,12 +713,175 @@ pub async fn rebuild(ctx: Context<'_>) -> Result<(), Error> {
 		.await?;
 
 		ctx.data().spell_map.write().await.insert(guild_id, sm);
+		super::settings::announce(ctx, "Spell lists were rebuilt with the latest tomes.").await;
 	} else {
 		ctx.say("Error: Must be ran in a guild").await?;
 	}
 	Ok(())
 }
 
-async fn get_spells(tome: &GuildTome) -> anyhow::Result<SpellCollection> {
-	sources::get_spells(&tome.source).await
+async fn get_spells(
+	tome: &GuildTome,
+	uploads: &HashMap<String, (String, String)>,
+) -> anyhow::Result<SpellCollection> {
+	match tome.source.strip_prefix(UPLOAD_PREFIX) {
+		Some(name) => {
+			let (format, content) = uploads
+				.get(name)
+				.ok_or_else(|| anyhow!("Uploaded tome {name} is missing"))?;
+			sources::parse_tome(name, content.as_bytes(), format.as_str().into())
+		}
+		None => sources::get_spells(&tome.source).await,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn spell(name: &str, classes: &[&str], edition: Option<Edition>) -> Spell {
+		Spell {
+			name: name.to_string(),
+			level: 3,
+			school: SpellSchool::Evocation,
+			classes: classes.iter().map(ToString::to_string).collect(),
+			description: String::new(),
+			ritual: false,
+			concentration: false,
+			casting_time: String::new(),
+			range: String::new(),
+			components: String::new(),
+			duration: String::new(),
+			source: SpellSource {
+				edition,
+				..Default::default()
+			},
+		}
+	}
+
+	fn tome(
+		rank: TomeRank,
+		policy: DuplicatePolicy,
+		spells: Vec<Spell>,
+	) -> (TomeRank, DuplicatePolicy, SpellCollection) {
+		(rank, policy, SpellCollection::homebrew(0, spells))
+	}
+
+	#[test]
+	fn merge_keeps_the_winner_and_adds_classes() {
+		let mut winner = spell("Fireball", &["Wizard"], None);
+		winner.description = "Guild version".to_string();
+		let spells = merge_duplicates(
+			vec![
+				tome(TomeRank::Guild(0), DuplicatePolicy::Merge, vec![winner]),
+				tome(
+					TomeRank::Book,
+					DuplicatePolicy::Replace,
+					vec![spell("fireball", &["Sorcerer", "wizard"], None)],
+				),
+			],
+			Edition::Classic,
+		);
+
+		assert_eq!(spells.len(), 1);
+		assert_eq!(spells[0].description, "Guild version");
+		assert_eq!(spells[0].classes, ["Wizard", "Sorcerer"]);
+	}
+
+	#[test]
+	fn replace_keeps_only_the_winner() {
+		let spells = merge_duplicates(
+			vec![
+				tome(
+					TomeRank::Guild(0),
+					DuplicatePolicy::Replace,
+					vec![spell("Fireball", &["Wizard"], None)],
+				),
+				tome(
+					TomeRank::Book,
+					DuplicatePolicy::Merge,
+					vec![spell("Fireball", &["Sorcerer"], None)],
+				),
+			],
+			Edition::Classic,
+		);
+
+		assert_eq!(spells.len(), 1);
+		assert_eq!(spells[0].classes, ["Wizard"]);
+	}
+
+	#[test]
+	fn merge_takes_classes_from_every_spell_list() {
+		let mut lists = tome(TomeRank::Book, DuplicatePolicy::Replace, Vec::new());
+		lists
+			.2
+			.spell_lists
+			.insert("Druid".to_string(), vec!["fireball".to_string()]);
+		let spells = merge_duplicates(
+			vec![
+				tome(
+					TomeRank::Guild(0),
+					DuplicatePolicy::Merge,
+					vec![spell("Fireball", &["Wizard"], None)],
+				),
+				lists,
+			],
+			Edition::Classic,
+		);
+
+		assert_eq!(spells[0].classes, ["Wizard", "Druid"]);
+	}
+
+	#[test]
+	fn replace_takes_classes_only_from_its_own_spell_lists() {
+		let mut winner = tome(
+			TomeRank::Guild(0),
+			DuplicatePolicy::Replace,
+			vec![spell("Fireball", &["Wizard"], None)],
+		);
+		winner
+			.2
+			.spell_lists
+			.insert("Sorcerer".to_string(), vec!["Fireball".to_string()]);
+		let mut other = tome(
+			TomeRank::Book,
+			DuplicatePolicy::Merge,
+			vec![spell("Fireball", &["Cleric"], None)],
+		);
+		other
+			.2
+			.spell_lists
+			.insert("Druid".to_string(), vec!["Fireball".to_string()]);
+		let spells = merge_duplicates(vec![winner, other], Edition::Classic);
+
+		assert_eq!(spells.len(), 1);
+		assert_eq!(spells[0].classes, ["Wizard", "Sorcerer"]);
+	}
+
+	#[test]
+	fn preferred_edition_wins_between_equal_ranks() {
+		let tomes = || {
+			vec![
+				tome(
+					TomeRank::Book,
+					DuplicatePolicy::Replace,
+					vec![spell("Fireball", &["Wizard"], Some(Edition::Classic))],
+				),
+				tome(
+					TomeRank::Book,
+					DuplicatePolicy::Merge,
+					vec![spell("Fireball", &["Sorcerer"], Some(Edition::Modern))],
+				),
+			]
+		};
+
+		let spells = merge_duplicates(tomes(), Edition::Modern);
+		assert_eq!(spells.len(), 1);
+		assert_eq!(spells[0].source.edition, Some(Edition::Modern));
+		assert_eq!(spells[0].classes, ["Sorcerer", "Wizard"]);
+
+		let spells = merge_duplicates(tomes(), Edition::Classic);
+		assert_eq!(spells[0].source.edition, Some(Edition::Classic));
+		assert_eq!(spells[0].classes, ["Wizard"]);
+	}
 }
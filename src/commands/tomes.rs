@@ -1,15 +1,42 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use diesel::prelude::*;
-use poise::serenity_prelude as serenity;
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
 
 use super::is_manager;
-use crate::{models::*, Context, Error};
+use crate::{
+	data::{
+		diff::diff,
+		sources::{self, TomeFormat},
+		DuplicatePolicy, SpellCollection,
+	},
+	models::*,
+	schema::{GuildTomes, TomeSnapshots, UploadedTomes},
+	Context, Error,
+};
+
+/// Number of snapshots kept per source.
+const SNAPSHOTS_KEPT: usize = 10;
+
+/// Guild tome sources starting with this are loaded from `UploadedTomes` instead of fetched.
+pub const UPLOAD_PREFIX: &str = "upload:";
+
+/// Uploads larger than this are refused, to keep builds and the database sane.
+const MAX_UPLOAD_SIZE: u64 = 4 * 1024 * 1024;
 
 /// Manage sources of homebrew spells. (Avrae tomes, etc.)
 #[poise::command(
 	prefix_command,
 	slash_command,
 	guild_only,
-	subcommands("list_tomes", "add_tome", "remove_tome")
+	subcommands(
+		"list_tomes",
+		"add_tome",
+		"remove_tome",
+		"upload_tome",
+		"configure_tome",
+		"diff_tomes"
+	)
 )]
 #[allow(clippy::unused_async)]
 pub async fn tomes(_ctx: Context<'_>) -> Result<(), Error> {
@@ -26,12 +53,16 @@ async fn list_tomes(ctx: Context<'_>) -> Result<(), Error> {
 
 	let tomes = GuildTomes
 		.filter(guild.eq(guild_id))
+		.order(crate::schema::GuildTomes::priority.desc())
 		.load::<GuildTome>(&mut *conn)
 		.expect("Error loading guild tomes.");
 
-	let str = tomes
-		.into_iter()
-		.fold(String::new(), |prev, val| format!("{prev}{}\n", val.source));
+	let str = tomes.into_iter().fold(String::new(), |prev, val| {
+		format!(
+			"{prev}{} (priority {}, {})\n",
+			val.source, val.priority, val.policy
+		)
+	});
 
 	ctx.say(str).await?;
 
@@ -49,6 +80,12 @@ async fn list_tomes(ctx: Context<'_>) -> Result<(), Error> {
 async fn add_tome(
 	ctx: Context<'_>,
 	#[description = "The tome to add (Avrae tome id)"] src: String,
+	#[description = "Higher priority tomes win when several have the same spell (default 0)"]
+	#[rename = "priority"]
+	tome_priority: Option<i32>,
+	#[description = "Whether duplicate spells keep other tomes' classes (default merge)"]
+	#[rename = "duplicates"]
+	duplicates: Option<DuplicatePolicy>,
 ) -> Result<(), Error> {
 	use crate::schema::GuildTomes::dsl::*;
 
@@ -66,6 +103,8 @@ async fn add_tome(
 		let tome = NewGuildTome {
 			guild: guild_id,
 			source: &src,
+			priority: tome_priority.unwrap_or_default(),
+			policy: duplicates.unwrap_or_default().as_str(),
 		};
 
 		diesel::insert_into(GuildTomes)
@@ -105,6 +144,15 @@ async fn remove_tome(
 	)
 	.execute(&mut *conn)?;
 
+	if let Some(name) = src.strip_prefix(UPLOAD_PREFIX) {
+		diesel::delete(
+			UploadedTomes::table
+				.filter(UploadedTomes::guild.eq(guild_id))
+				.filter(UploadedTomes::name.eq(name)),
+		)
+		.execute(&mut *conn)?;
+	}
+
 	if count > 0 {
 		ctx.say(format!("Successfully removed: {src}")).await?;
 	} else {
@@ -114,3 +162,302 @@ async fn remove_tome(
 
 	Ok(())
 }
+
+/// Upload a tome file for this guild. Uploading a file with the same name replaces it.
+#[poise::command(slash_command, guild_only, rename = "upload", check = "is_manager")]
+async fn upload_tome(
+	ctx: Context<'_>,
+	#[description = "The tome file (JSON, YAML or TOML)"] file: serenity::Attachment,
+	#[description = "Name of the tome (defaults to the file name)"]
+	#[max_length = 100]
+	name: Option<String>,
+) -> Result<(), Error> {
+	if file.size > MAX_UPLOAD_SIZE {
+		ctx.say("This file is too large to be a tome.").await?;
+		return Ok(());
+	}
+
+	let name = name.unwrap_or_else(|| {
+		file.filename
+			.rsplit_once('.')
+			.map_or(file.filename.as_str(), |(stem, _)| stem)
+			.to_string()
+	});
+
+	let format = TomeFormat::detect(file.content_type.as_deref(), &file.filename);
+	let content = file.download().await?;
+	let collection = match sources::parse_tome(&name, &content, format) {
+		Ok(collection) => collection,
+		Err(err) => {
+			ctx.say(format!("This is not a valid tome: {err}")).await?;
+			return Ok(());
+		}
+	};
+	let content = String::from_utf8(content)?;
+
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let src = format!("{UPLOAD_PREFIX}{name}");
+
+	let mut conn = ctx.data().db.lock().await;
+	let replaced = conn.transaction::<_, Error, _>(|conn| {
+		let replaced = diesel::update(
+			UploadedTomes::table
+				.filter(UploadedTomes::guild.eq(guild_id))
+				.filter(UploadedTomes::name.eq(&name)),
+		)
+		.set((
+			UploadedTomes::format.eq(format.name()),
+			UploadedTomes::content.eq(&content),
+		))
+		.execute(conn)?
+			> 0;
+
+		if !replaced {
+			diesel::insert_into(UploadedTomes::table)
+				.values(&NewUploadedTome {
+					guild: guild_id,
+					name: &name,
+					format: format.name(),
+					content: &content,
+				})
+				.execute(conn)?;
+
+			diesel::insert_into(GuildTomes::table)
+				.values(&NewGuildTome {
+					guild: guild_id,
+					source: &src,
+					priority: 0,
+					policy: DuplicatePolicy::default().as_str(),
+				})
+				.execute(conn)?;
+		}
+
+		Ok(replaced)
+	})?;
+
+	let mut title = collection.name.clone();
+	if let Some(version) = &collection.version {
+		title += " v";
+		title += version;
+	}
+	if let Some(author) = &collection.author {
+		title += " by ";
+		title += author;
+	}
+
+	ctx.say(format!(
+		"Successfully {} {src}: {title} ({} spells, {} spell lists, {} classes). Use `/rebuild` to apply it.",
+		if replaced { "replaced" } else { "uploaded" },
+		collection.spells.len(),
+		collection.spell_lists.len(),
+		collection.progressions.len()
+	))
+	.await?;
+
+	Ok(())
+}
+
+/// Change how a tome ranks against others when they have spells with the same name.
+#[poise::command(
+	prefix_command,
+	slash_command,
+	guild_only,
+	rename = "configure",
+	check = "is_manager"
+)]
+async fn configure_tome(
+	ctx: Context<'_>,
+	#[description = "The tome to configure"] src: String,
+	#[description = "Higher priority tomes win when several have the same spell"]
+	priority: Option<i32>,
+	#[description = "Whether duplicate spells keep other tomes' classes"]
+	duplicates: Option<DuplicatePolicy>,
+) -> Result<(), Error> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let mut conn = ctx.data().db.lock().await;
+
+	let Some(tome) = GuildTomes::table
+		.filter(GuildTomes::guild.eq(guild_id))
+		.filter(GuildTomes::source.eq(&src))
+		.first::<GuildTome>(&mut *conn)
+		.optional()?
+	else {
+		ctx.say(format!("{src} is not added for this guild."))
+			.await?;
+		return Ok(());
+	};
+
+	let priority = priority.unwrap_or(tome.priority);
+	let duplicates = duplicates.unwrap_or_else(|| tome.policy.parse().unwrap_or_default());
+	diesel::update(GuildTomes::table.filter(GuildTomes::id.eq(tome.id)))
+		.set((
+			GuildTomes::priority.eq(priority),
+			GuildTomes::policy.eq(duplicates.as_str()),
+		))
+		.execute(&mut *conn)?;
+
+	ctx.say(format!(
+		"{src}: priority {priority}, {} duplicates. Use `/rebuild` to apply it.",
+		duplicates.as_str()
+	))
+	.await?;
+
+	Ok(())
+}
+
+/// FNV-1a, so hashes stay comparable across builds and restarts.
+fn content_hash(content: &str) -> u64 {
+	content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+		(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+	})
+}
+
+/// Serializes a fetched source for [`save_snapshot`], along with its hash.
+pub fn snapshot(collection: &SpellCollection) -> serde_json::Result<(String, u64)> {
+	let content = serde_json::to_string(collection)?;
+	let hash = content_hash(&content);
+	Ok((content, hash))
+}
+
+/// Stores a snapshot of a guild's tome if its content changed since the last one. Each guild
+/// keeps its own history, even of the same source.
+pub fn save_snapshot(
+	conn: &mut MysqlConnection,
+	guild: u64,
+	src: &str,
+	content: &str,
+	hash: u64,
+) -> Result<(), Error> {
+	let ids: Vec<(u32, u64)> = TomeSnapshots::table
+		.filter(TomeSnapshots::guild.eq(guild))
+		.filter(TomeSnapshots::source.eq(src))
+		.order(TomeSnapshots::id.desc())
+		.select((TomeSnapshots::id, TomeSnapshots::hash))
+		.load(conn)?;
+
+	if ids.first().is_some_and(|(_, latest)| *latest == hash) {
+		return Ok(());
+	}
+
+	let created = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+	diesel::insert_into(TomeSnapshots::table)
+		.values(&NewTomeSnapshot {
+			guild,
+			source: src,
+			hash,
+			created,
+			content,
+		})
+		.execute(conn)?;
+
+	let stale: Vec<u32> = ids
+		.iter()
+		.skip(SNAPSHOTS_KEPT - 1)
+		.map(|(id, _)| *id)
+		.collect();
+	if !stale.is_empty() {
+		diesel::delete(TomeSnapshots::table.filter(TomeSnapshots::id.eq_any(stale)))
+			.execute(conn)?;
+	}
+
+	Ok(())
+}
+
+fn load_snapshots(
+	conn: &mut MysqlConnection,
+	guild: u64,
+	src: &str,
+	count: i64,
+) -> QueryResult<Vec<TomeSnapshot>> {
+	TomeSnapshots::table
+		.filter(TomeSnapshots::guild.eq(guild))
+		.filter(TomeSnapshots::source.eq(src))
+		.order(TomeSnapshots::id.desc())
+		.select((TomeSnapshots::source, TomeSnapshots::content))
+		.limit(count)
+		.load(conn)
+}
+
+async fn autocomplete_snapshot(ctx: Context<'_>, partial: &str) -> Vec<String> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().unwrap_or_default();
+	let mut conn = ctx.data().db.lock().await;
+
+	TomeSnapshots::table
+		.filter(TomeSnapshots::guild.eq(guild_id))
+		.select(TomeSnapshots::source)
+		.distinct()
+		.order(TomeSnapshots::source)
+		.load::<String>(&mut *conn)
+		.unwrap_or_default()
+		.into_iter()
+		.filter(|src| src.to_lowercase().contains(&partial.to_lowercase()))
+		.take(25)
+		.collect()
+}
+
+/// Show what changed in a tome since its previous build, or between two tomes.
+#[poise::command(prefix_command, slash_command, guild_only, rename = "diff")]
+async fn diff_tomes(
+	ctx: Context<'_>,
+	#[description = "The tome to compare"]
+	#[autocomplete = "autocomplete_snapshot"]
+	src: String,
+	#[description = "Compare against this tome instead of the previous build"]
+	#[autocomplete = "autocomplete_snapshot"]
+	other: Option<String>,
+) -> Result<(), Error> {
+	let serenity::GuildId(guild_id) = ctx.guild_id().expect("Guild Id");
+	let snapshots = {
+		let mut conn = ctx.data().db.lock().await;
+		match &other {
+			Some(other) => {
+				let mut snapshots = load_snapshots(&mut conn, guild_id, &src, 1)?;
+				snapshots.extend(load_snapshots(&mut conn, guild_id, other, 1)?);
+				snapshots
+			}
+			None => load_snapshots(&mut conn, guild_id, &src, 2)?,
+		}
+	};
+
+	let [new, old] = snapshots.as_slice() else {
+		ctx.say(match other {
+			Some(other) => format!("No snapshots for both {src} and {other} yet."),
+			None => format!("{src} has not changed since it was first built."),
+		})
+		.await?;
+		return Ok(());
+	};
+
+	let changes = diff(
+		&serde_json::from_str(&old.content)?,
+		&serde_json::from_str(&new.content)?,
+	);
+	if changes.is_empty() {
+		ctx.say(format!(
+			"No differences between {} and {}.",
+			old.source, new.source
+		))
+		.await?;
+		return Ok(());
+	}
+
+	let mut embed = CreateEmbed::default();
+	embed.title(if other.is_some() {
+		format!("{} → {}", old.source, new.source)
+	} else {
+		format!("Changes in {src}")
+	});
+	embed.footer(|f| {
+		f.text(format!(
+			"+{} −{} ~{}",
+			changes.added.len(),
+			changes.removed.len(),
+			changes.changed.len()
+		))
+	});
+
+	let lines = changes.lines().into_iter().map(Into::into).collect();
+	super::send_paginated_message(ctx, lines, embed, false).await?;
+
+	Ok(())
+}
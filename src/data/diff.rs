@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{SpellCollection, SpellSchool};
+
+/// A spell as seen by a single collection, with class lists from `spell_lists` folded in.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Entry {
+	name: String,
+	level: Option<u8>,
+	school: Option<SpellSchool>,
+	classes: BTreeSet<String>,
+	description: String,
+}
+
+fn entries(collection: &SpellCollection) -> BTreeMap<String, Entry> {
+	let mut entries: BTreeMap<String, Entry> = collection
+		.spells
+		.iter()
+		.map(|spell| {
+			(
+				spell.name.to_lowercase(),
+				Entry {
+					name: spell.name.clone(),
+					level: Some(spell.level),
+					school: Some(spell.school.clone()),
+					classes: spell.classes.iter().cloned().collect(),
+					description: spell.description.clone(),
+				},
+			)
+		})
+		.collect();
+
+	for (class, spells) in &collection.spell_lists {
+		for name in spells {
+			entries
+				.entry(name.to_lowercase())
+				.or_insert_with(|| Entry {
+					name: name.clone(),
+					..Default::default()
+				})
+				.classes
+				.insert(class.clone());
+		}
+	}
+
+	entries
+}
+
+#[derive(Debug, Default)]
+pub struct TomeDiff {
+	pub added: Vec<String>,
+	pub removed: Vec<String>,
+	/// Spell name and a description of each change.
+	pub changed: Vec<(String, Vec<String>)>,
+}
+
+impl TomeDiff {
+	pub fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+	}
+
+	/// Renders the diff as lines, with a header for each section.
+	pub fn lines(&self) -> Vec<String> {
+		let mut lines = Vec::new();
+		if !self.added.is_empty() {
+			lines.push(format!("**Added ({})**", self.added.len()));
+			lines.extend(self.added.iter().map(|name| format!("+ {name}")));
+		}
+		if !self.removed.is_empty() {
+			lines.push(format!("**Removed ({})**", self.removed.len()));
+			lines.extend(self.removed.iter().map(|name| format!("− {name}")));
+		}
+		if !self.changed.is_empty() {
+			lines.push(format!("**Changed ({})**", self.changed.len()));
+			lines.extend(
+				self.changed
+					.iter()
+					.map(|(name, changes)| format!("{name}: {}", changes.join("; "))),
+			);
+		}
+		lines
+	}
+}
+
+fn describe<T: std::fmt::Debug>(value: Option<&T>) -> String {
+	value.map_or_else(|| "unknown".to_string(), |v| format!("{v:?}"))
+}
+
+/// Compares two collections spell by spell.
+pub fn diff(old: &SpellCollection, new: &SpellCollection) -> TomeDiff {
+	let old = entries(old);
+	let new = entries(new);
+	let mut diff = TomeDiff::default();
+
+	for (key, entry) in &new {
+		let Some(previous) = old.get(key) else {
+			diff.added.push(entry.name.clone());
+			continue;
+		};
+
+		let mut changes = Vec::new();
+		if previous.level != entry.level {
+			changes.push(format!(
+				"level {} → {}",
+				describe(previous.level.as_ref()),
+				describe(entry.level.as_ref())
+			));
+		}
+		if previous.school != entry.school {
+			changes.push(format!(
+				"school {} → {}",
+				describe(previous.school.as_ref()),
+				describe(entry.school.as_ref())
+			));
+		}
+
+		let gained: Vec<_> = entry.classes.difference(&previous.classes).collect();
+		let lost: Vec<_> = previous.classes.difference(&entry.classes).collect();
+		if !gained.is_empty() || !lost.is_empty() {
+			let classes = gained
+				.iter()
+				.map(|class| format!("+{class}"))
+				.chain(lost.iter().map(|class| format!("−{class}")))
+				.collect::<Vec<_>>()
+				.join(" ");
+			changes.push(format!("classes {classes}"));
+		}
+		if previous.description != entry.description {
+			changes.push("description edited".to_string());
+		}
+
+		if !changes.is_empty() {
+			diff.changed.push((entry.name.clone(), changes));
+		}
+	}
+
+	diff.removed = old
+		.iter()
+		.filter(|(key, _)| !new.contains_key(*key))
+		.map(|(_, entry)| entry.name.clone())
+		.collect();
+
+	diff
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::{Spell, SpellSource};
+
+	fn spell(name: &str, level: u8, classes: &[&str]) -> Spell {
+		Spell {
+			name: name.to_string(),
+			level,
+			school: SpellSchool::Evocation,
+			classes: classes.iter().map(ToString::to_string).collect(),
+			description: String::new(),
+			ritual: false,
+			concentration: false,
+			casting_time: String::new(),
+			range: String::new(),
+			components: String::new(),
+			duration: String::new(),
+			source: SpellSource::default(),
+		}
+	}
+
+	#[test]
+	fn finds_added_removed_and_changed_spells() {
+		let old = SpellCollection::homebrew(
+			0,
+			vec![spell("Fireball", 3, &["Wizard"]), spell("Shield", 1, &[])],
+		);
+		let new = SpellCollection::homebrew(
+			0,
+			vec![spell("fireball", 4, &["Sorcerer"]), spell("Bless", 1, &[])],
+		);
+		let diff = diff(&old, &new);
+
+		assert_eq!(diff.added, ["Bless"]);
+		assert_eq!(diff.removed, ["Shield"]);
+		assert_eq!(
+			diff.changed,
+			[(
+				"fireball".to_string(),
+				vec![
+					"level 3 → 4".to_string(),
+					"classes +Sorcerer −Wizard".to_string()
+				]
+			)]
+		);
+	}
+
+	#[test]
+	fn identical_collections_have_no_diff() {
+		let tome = || SpellCollection::homebrew(0, vec![spell("Fireball", 3, &["Wizard"])]);
+		assert!(diff(&tome(), &tome()).is_empty());
+	}
+}
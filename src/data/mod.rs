@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use progression::Progression;
+
+pub mod diff;
+pub mod progression;
+pub mod scroll;
 pub mod sources;
 
 #[derive(Clone, Default, Debug, Serialize, Deserialize, poise::ChoiceParameter, PartialEq, Eq)]
@@ -81,30 +86,226 @@ impl From<String> for SpellSchool {
 // 	}
 // }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+/// How a tome's spells treat copies of the same spell from lower priority tomes.
+#[derive(Debug, Clone, Copy, Default, poise::ChoiceParameter, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+	/// This tome's version wins, but keeps the classes of the other copies.
+	#[default]
+	Merge,
+	/// This tome's version replaces the other copies wholesale.
+	Replace,
+}
+
+/// Whether a spell comes from a published book or from homebrew.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, poise::ChoiceParameter, PartialEq, Eq)]
+pub enum SpellOrigin {
+	Official,
+	Homebrew,
+}
+
+impl SpellOrigin {
+	pub fn matches(self, source: &SpellSource) -> bool {
+		match self {
+			Self::Official => source.official,
+			Self::Homebrew => !source.official,
+		}
+	}
+}
+
+impl DuplicatePolicy {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Merge => "merge",
+			Self::Replace => "replace",
+		}
+	}
+}
+
+/// Rules edition a spell was published for.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, poise::ChoiceParameter, PartialEq, Eq)]
+pub enum Edition {
+	#[default]
+	#[name = "2014"]
+	#[serde(rename = "2014")]
+	Classic,
+	#[name = "2024"]
+	#[serde(rename = "2024")]
+	Modern,
+}
+
+impl Edition {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Classic => "2014",
+			Self::Modern => "2024",
+		}
+	}
+}
+
+/// Where a spell was published.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SpellSource {
+	/// Book abbreviation like `PHB`, or the name of the tome the spell came from.
+	pub book: String,
+	pub page: Option<u32>,
+	pub edition: Option<Edition>,
+	/// Id of the tome the spell was loaded from, as given to `/tomes add`.
+	#[serde(skip)]
+	pub tome: String,
+	/// Whether the tome is a published book rather than homebrew.
+	#[serde(skip)]
+	pub official: bool,
+}
+
+impl SpellSource {
+	/// Whether `name` is the spell's book or tome.
+	pub fn is(&self, name: &str) -> bool {
+		self.book.eq_ignore_ascii_case(name) || self.tome.eq_ignore_ascii_case(name)
+	}
+}
+
+impl std::fmt::Display for SpellSource {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.book)?;
+		if let Some(page) = self.page {
+			write!(f, " p. {page}")?;
+		}
+		if let Some(edition) = self.edition {
+			write!(f, " ({})", edition.as_str())?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Spell {
 	pub name: String,
 	pub level: u8,
 	pub school: SpellSchool,
+	#[serde(default)]
 	pub classes: Vec<String>,
 
+	#[serde(default)]
 	pub description: String,
+	#[serde(default)]
 	pub ritual: bool,
+	#[serde(default)]
+	pub concentration: bool,
+
+	#[serde(default)]
+	pub casting_time: String,
+	#[serde(default)]
+	pub range: String,
+	#[serde(default)]
+	pub components: String,
+	#[serde(default)]
+	pub duration: String,
+
+	#[serde(default)]
+	pub source: SpellSource,
+}
+
+impl Spell {
+	/// The spell's name, followed by its book when it is known.
+	pub fn label(&self) -> String {
+		if self.source.book.is_empty() {
+			self.name.clone()
+		} else {
+			format!("{} *({})*", self.name, self.source.book)
+		}
+	}
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SpellCollection {
 	id: Source,
 	pub name: String,
 	image: Option<String>,
+	#[serde(default)]
+	pub author: Option<String>,
+	#[serde(default)]
+	pub version: Option<String>,
 
 	pub spells: Vec<Spell>,
 	pub spell_lists: HashMap<String, Vec<String>>,
+	pub progressions: HashMap<String, Progression>,
+	#[serde(default)]
+	pub classes: HashMap<String, ClassInfo>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Descriptive information a tome can give about its classes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ClassInfo {
+	pub description: Option<String>,
+	pub image: Option<String>,
+	pub subclasses: Vec<String>,
+}
+
+impl SpellCollection {
+	/// The built-in tome holding a guild's own homebrew spells.
+	pub fn homebrew(guild: u64, spells: Vec<Spell>) -> Self {
+		Self {
+			id: Source::Homebrew(guild),
+			name: "Homebrew".to_string(),
+			image: None,
+			author: None,
+			version: None,
+			spells,
+			spell_lists: HashMap::new(),
+			progressions: HashMap::new(),
+			classes: HashMap::new(),
+		}
+	}
+
+	/// Records the tome each spell came from, and attributes spells that don't name their book
+	/// to the tome itself.
+	pub fn attribute_spells(&mut self) {
+		let tome = self.id.key();
+		let official = self.id.is_official();
+		for spell in &mut self.spells {
+			if spell.source.book.is_empty() {
+				spell.source.book.clone_from(&self.name);
+			}
+			spell.source.tome.clone_from(&tome);
+			spell.source.official = official;
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Source {
 	Avrae(String),
 	FiveE(String),
+	FiveEHomebrew(String),
 	Json(String),
+	Homebrew(u64),
+	Open5e(String),
+	Foundry(String),
+}
+
+impl Source {
+	/// The id of the tome, as given to `/tomes add`.
+	pub fn key(&self) -> String {
+		match self {
+			Self::Avrae(id)
+			| Self::FiveE(id)
+			| Self::FiveEHomebrew(id)
+			| Self::Json(id) => id.clone(),
+			Self::Homebrew(_) => "homebrew".to_string(),
+			Self::Open5e(slug) => format!("{}{slug}", sources::OPEN5E_PREFIX),
+			Self::Foundry(source) => format!("{}{source}", sources::FOUNDRY_PREFIX),
+		}
+	}
+
+	/// Published books and the SRD, as opposed to homebrew of any kind.
+	pub fn is_official(&self) -> bool {
+		match self {
+			Self::FiveE(_) => true,
+			Self::Avrae(id) => id == "srd",
+			Self::Open5e(slug) => slug == "wotc-srd",
+			Self::FiveEHomebrew(_) | Self::Json(_) | Self::Homebrew(_) | Self::Foundry(_) => false,
+		}
+	}
 }
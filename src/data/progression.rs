@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// How a class gains spell slots as it levels up.
+#[derive(
+	Debug, Clone, Copy, Default, Serialize, Deserialize, poise::ChoiceParameter, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CasterType {
+	#[default]
+	None,
+	Full,
+	Half,
+	/// Half caster that rounds its caster level up, like the Artificer.
+	#[name = "Half (rounded up)"]
+	HalfUp,
+	Third,
+	Pact,
+}
+
+/// How a class decides which of its spells it can cast.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Preparation {
+	#[default]
+	None,
+	/// Ability modifier plus class level divided by `level_divisor` (minimum of one).
+	Prepared { level_divisor: u8 },
+	/// Fixed number of spells known, indexed by class level.
+	Known(Vec<u8>),
+}
+
+/// Spellcasting progression of a class, as declared in a tome:
+///
+/// ```json
+/// "classes": {
+///   "Witch": {
+///     "caster": "full",
+///     "preparation": { "known": [4, 5, 6, 7, 8, 9, 10, 11, 12, 14, 15, 15, 16, 18, 19, 19, 20, 22, 22, 22] },
+///     "cantrips_known": [4, 4, 4, 5, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Progression {
+	pub caster: CasterType,
+	pub preparation: Preparation,
+	pub cantrips_known: Vec<u8>,
+}
+
+const FULL_CASTER_SLOTS: [[u8; 9]; 20] = [
+	[2, 0, 0, 0, 0, 0, 0, 0, 0],
+	[3, 0, 0, 0, 0, 0, 0, 0, 0],
+	[4, 2, 0, 0, 0, 0, 0, 0, 0],
+	[4, 3, 0, 0, 0, 0, 0, 0, 0],
+	[4, 3, 2, 0, 0, 0, 0, 0, 0],
+	[4, 3, 3, 0, 0, 0, 0, 0, 0],
+	[4, 3, 3, 1, 0, 0, 0, 0, 0],
+	[4, 3, 3, 2, 0, 0, 0, 0, 0],
+	[4, 3, 3, 3, 1, 0, 0, 0, 0],
+	[4, 3, 3, 3, 2, 0, 0, 0, 0],
+	[4, 3, 3, 3, 2, 1, 0, 0, 0],
+	[4, 3, 3, 3, 2, 1, 0, 0, 0],
+	[4, 3, 3, 3, 2, 1, 1, 0, 0],
+	[4, 3, 3, 3, 2, 1, 1, 0, 0],
+	[4, 3, 3, 3, 2, 1, 1, 1, 0],
+	[4, 3, 3, 3, 2, 1, 1, 1, 0],
+	[4, 3, 3, 3, 2, 1, 1, 1, 1],
+	[4, 3, 3, 3, 3, 1, 1, 1, 1],
+	[4, 3, 3, 3, 3, 2, 1, 1, 1],
+	[4, 3, 3, 3, 3, 2, 2, 1, 1],
+];
+
+impl CasterType {
+	/// Effective caster level on the full caster slot table.
+	pub fn caster_level(self, level: u8) -> u8 {
+		match self {
+			Self::Full => level,
+			Self::Half if level >= 2 => level.div_ceil(2),
+			Self::HalfUp => level.div_ceil(2),
+			Self::Third if level >= 3 => level.div_ceil(3),
+			_ => 0,
+		}
+	}
+
+	/// Spell slots per spell level (1st through 9th).
+	pub fn slots(self, level: u8) -> [u8; 9] {
+		match self.caster_level(level.min(20)) {
+			0 => [0; 9],
+			l => FULL_CASTER_SLOTS[usize::from(l) - 1],
+		}
+	}
+
+	/// Number of pact magic slots and their spell level.
+	pub fn pact_slots(self, level: u8) -> Option<(u8, u8)> {
+		if self != Self::Pact {
+			return None;
+		}
+
+		Some(match level {
+			0 => return None,
+			1 => (1, 1),
+			2 => (2, 1),
+			3..=10 => (2, level.div_ceil(2)),
+			11..=16 => (3, 5),
+			_ => (4, 5),
+		})
+	}
+}
+
+impl Progression {
+	/// Number of spells that can be prepared (or are known) at the given class level.
+	///
+	/// Prepared casters need an ability modifier, so this returns `None` without one.
+	pub fn spells(&self, level: u8, modifier: Option<i8>) -> Option<u8> {
+		match &self.preparation {
+			Preparation::None => None,
+			Preparation::Prepared { level_divisor } => {
+				let base = i16::from(level / (*level_divisor).max(1));
+				let count = (base + i16::from(modifier?)).max(1);
+				u8::try_from(count).ok()
+			}
+			Preparation::Known(table) => table_lookup(table, level),
+		}
+	}
+
+	pub fn cantrips(&self, level: u8) -> Option<u8> {
+		table_lookup(&self.cantrips_known, level)
+	}
+}
+
+fn table_lookup(table: &[u8], level: u8) -> Option<u8> {
+	if table.is_empty() || level == 0 {
+		None
+	} else {
+		table
+			.get(usize::from(level) - 1)
+			.or_else(|| table.last())
+			.copied()
+	}
+}
+
+/// Progressions of the official spellcasting classes, keyed by lowercase class name.
+pub fn default_progressions() -> HashMap<String, Progression> {
+	let prepared = |caster, level_divisor, cantrips_known: &[u8]| Progression {
+		caster,
+		preparation: Preparation::Prepared { level_divisor },
+		cantrips_known: cantrips_known.to_vec(),
+	};
+	let known = |caster, known: &[u8], cantrips_known: &[u8]| Progression {
+		caster,
+		preparation: Preparation::Known(known.to_vec()),
+		cantrips_known: cantrips_known.to_vec(),
+	};
+
+	let cantrips_2 = [2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4];
+	let cantrips_3 = [3, 3, 3, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5];
+	let cantrips_4 = [4, 4, 4, 5, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6];
+	let artificer_cantrips = [2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4];
+
+	[
+		(
+			"artificer",
+			prepared(CasterType::HalfUp, 2, &artificer_cantrips),
+		),
+		(
+			"bard",
+			known(
+				CasterType::Full,
+				&[
+					4, 5, 6, 7, 8, 9, 10, 11, 12, 14, 15, 15, 16, 18, 19, 19, 20, 22, 22, 22,
+				],
+				&cantrips_2,
+			),
+		),
+		("cleric", prepared(CasterType::Full, 1, &cantrips_3)),
+		("druid", prepared(CasterType::Full, 1, &cantrips_2)),
+		("paladin", prepared(CasterType::Half, 2, &[])),
+		(
+			"ranger",
+			known(
+				CasterType::Half,
+				&[
+					0, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11,
+				],
+				&[],
+			),
+		),
+		(
+			"sorcerer",
+			known(
+				CasterType::Full,
+				&[
+					2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 12, 13, 13, 14, 14, 15, 15, 15, 15,
+				],
+				&cantrips_4,
+			),
+		),
+		(
+			"warlock",
+			known(
+				CasterType::Pact,
+				&[
+					2, 3, 4, 5, 6, 7, 8, 9, 10, 10, 11, 11, 12, 12, 13, 13, 14, 14, 15, 15,
+				],
+				&cantrips_2,
+			),
+		),
+		("wizard", prepared(CasterType::Full, 1, &cantrips_3)),
+	]
+	.into_iter()
+	.map(|(class, progression)| (class.to_string(), progression))
+	.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn full_caster_slots() {
+		assert_eq!(CasterType::Full.slots(1), [2, 0, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::Full.slots(5), [4, 3, 2, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::Full.slots(20), [4, 3, 3, 3, 3, 2, 2, 1, 1]);
+		assert_eq!(CasterType::Full.slots(30), CasterType::Full.slots(20));
+	}
+
+	#[test]
+	fn half_caster_slots() {
+		assert_eq!(CasterType::Half.slots(1), [0; 9]);
+		assert_eq!(CasterType::Half.slots(2), [2, 0, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::Half.slots(5), [4, 2, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::Half.slots(20), [4, 3, 3, 3, 2, 0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn half_up_caster_slots() {
+		assert_eq!(CasterType::HalfUp.slots(1), [2, 0, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::HalfUp.slots(3), [3, 0, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::HalfUp.slots(20), [4, 3, 3, 3, 2, 0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn third_caster_slots() {
+		assert_eq!(CasterType::Third.slots(2), [0; 9]);
+		assert_eq!(CasterType::Third.slots(3), [2, 0, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::Third.slots(7), [4, 2, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(CasterType::Third.slots(20), [4, 3, 3, 1, 0, 0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn artificer_progression() {
+		let artificer = &default_progressions()["artificer"];
+		assert_eq!(artificer.caster, CasterType::HalfUp);
+		assert_eq!(artificer.cantrips(1), Some(2));
+		assert_eq!(artificer.cantrips(9), Some(2));
+		assert_eq!(artificer.cantrips(10), Some(3));
+		assert_eq!(artificer.cantrips(13), Some(3));
+		assert_eq!(artificer.cantrips(14), Some(4));
+		assert_eq!(artificer.cantrips(20), Some(4));
+		assert_eq!(artificer.spells(5, Some(3)), Some(5));
+	}
+
+	#[test]
+	fn pact_slots() {
+		assert_eq!(CasterType::Pact.slots(5), [0; 9]);
+		assert_eq!(CasterType::Pact.pact_slots(0), None);
+		assert_eq!(CasterType::Pact.pact_slots(1), Some((1, 1)));
+		assert_eq!(CasterType::Pact.pact_slots(2), Some((2, 1)));
+		assert_eq!(CasterType::Pact.pact_slots(3), Some((2, 2)));
+		assert_eq!(CasterType::Pact.pact_slots(10), Some((2, 5)));
+		assert_eq!(CasterType::Pact.pact_slots(11), Some((3, 5)));
+		assert_eq!(CasterType::Pact.pact_slots(17), Some((4, 5)));
+		assert_eq!(CasterType::Full.pact_slots(5), None);
+	}
+}
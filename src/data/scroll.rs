@@ -0,0 +1,137 @@
+/// Magic item rarity, as used for spell scrolls.
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rarity {
+	Common,
+	Uncommon,
+	Rare,
+	#[name = "Very rare"]
+	VeryRare,
+	Legendary,
+}
+
+impl Rarity {
+	/// Spell levels whose scrolls have this rarity.
+	pub fn spell_levels(self) -> (u8, u8) {
+		match self {
+			Self::Common => (0, 1),
+			Self::Uncommon => (2, 3),
+			Self::Rare => (4, 5),
+			Self::VeryRare => (6, 8),
+			Self::Legendary => (9, 9),
+		}
+	}
+
+	/// Price range in gp (DMG, p. 135). Legendary items have no upper bound.
+	pub fn price(self) -> (u32, Option<u32>) {
+		match self {
+			Self::Common => (50, Some(100)),
+			Self::Uncommon => (101, Some(500)),
+			Self::Rare => (501, Some(5_000)),
+			Self::VeryRare => (5_001, Some(50_000)),
+			Self::Legendary => (50_001, None),
+		}
+	}
+
+	/// Crafting cost in gp and minimum creator level (DMG, p. 129).
+	pub fn dmg_crafting(self) -> (u32, u8) {
+		match self {
+			Self::Common => (100, 3),
+			Self::Uncommon => (500, 3),
+			Self::Rare => (5_000, 6),
+			Self::VeryRare => (50_000, 11),
+			Self::Legendary => (500_000, 17),
+		}
+	}
+}
+
+/// Save DC, attack bonus and rarity of a spell scroll (DMG, p. 200).
+#[derive(Debug, Clone, Copy)]
+pub struct Scroll {
+	pub level: u8,
+	pub save_dc: u8,
+	pub attack_bonus: u8,
+	pub rarity: Rarity,
+}
+
+impl Scroll {
+	pub fn new(level: u8) -> Self {
+		let (save_dc, attack_bonus) = match level {
+			0..=2 => (13, 5),
+			3..=4 => (15, 7),
+			5..=6 => (17, 9),
+			7..=8 => (18, 10),
+			_ => (19, 11),
+		};
+		let rarity = match level {
+			0..=1 => Rarity::Common,
+			2..=3 => Rarity::Uncommon,
+			4..=5 => Rarity::Rare,
+			6..=8 => Rarity::VeryRare,
+			_ => Rarity::Legendary,
+		};
+
+		Self {
+			level,
+			save_dc,
+			attack_bonus,
+			rarity,
+		}
+	}
+
+	/// Crafting cost in gp and time in days under the DMG rules (25 gp of progress per day).
+	pub fn dmg_crafting(self) -> (u32, u32) {
+		let (cost, _) = self.rarity.dmg_crafting();
+		(cost, cost / 25)
+	}
+
+	/// Crafting time and cost in gp under Xanathar's Guide to Everything (p. 133).
+	pub fn xge_crafting(self) -> (&'static str, u32) {
+		match self.level {
+			0 => ("1 day", 15),
+			1 => ("1 day", 25),
+			2 => ("3 days", 250),
+			3 => ("1 workweek", 500),
+			4 => ("2 workweeks", 2_500),
+			5 => ("4 workweeks", 5_000),
+			6 => ("8 workweeks", 15_000),
+			7 => ("16 workweeks", 25_000),
+			8 => ("32 workweeks", 50_000),
+			_ => ("48 workweeks", 250_000),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn save_dc_and_attack_bonus_follow_spell_level() {
+		let stats = |level| {
+			let scroll = Scroll::new(level);
+			(scroll.save_dc, scroll.attack_bonus)
+		};
+		assert_eq!(stats(0), (13, 5));
+		assert_eq!(stats(2), (13, 5));
+		assert_eq!(stats(3), (15, 7));
+		assert_eq!(stats(5), (17, 9));
+		assert_eq!(stats(7), (18, 10));
+		assert_eq!(stats(9), (19, 11));
+	}
+
+	#[test]
+	fn rarity_matches_spell_levels() {
+		for level in 0..=9 {
+			let (min, max) = Scroll::new(level).rarity.spell_levels();
+			assert!((min..=max).contains(&level), "level {level}");
+		}
+	}
+
+	#[test]
+	fn prices_and_crafting() {
+		assert_eq!(Scroll::new(1).rarity.price(), (50, Some(100)));
+		assert_eq!(Scroll::new(9).rarity.price(), (50_001, None));
+		assert_eq!(Scroll::new(3).dmg_crafting(), (500, 20));
+		assert_eq!(Scroll::new(3).xge_crafting(), ("1 workweek", 500));
+	}
+}
@@ -6,7 +6,7 @@ use reqwest::get;
 use serde::Deserialize;
 use tokio::sync::OnceCell;
 
-use crate::data::{Source, Spell, SpellCollection, SpellSchool};
+use crate::data::{Edition, Source, Spell, SpellCollection, SpellSchool, SpellSource};
 
 const API_ENDPOINT: &str = "https://api.avrae.io";
 
@@ -68,12 +68,29 @@ struct AvraeApiResponse<T> {
 	data: Option<T>,
 }
 
-// #[derive(Debug, Clone, Deserialize)]
-// struct SpellComponents {
-// 	verbal: bool,
-// 	somatic: bool,
-// 	material: String,
-// }
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct SpellComponents {
+	verbal: bool,
+	somatic: bool,
+	material: Option<String>,
+}
+
+impl SpellComponents {
+	fn text(&self) -> String {
+		let mut components = Vec::new();
+		if self.verbal {
+			components.push("V".to_string());
+		}
+		if self.somatic {
+			components.push("S".to_string());
+		}
+		if let Some(material) = self.material.as_deref().filter(|m| !m.is_empty()) {
+			components.push(format!("M ({material})"));
+		}
+		components.join(", ")
+	}
+}
 
 #[derive(Debug, Clone, Deserialize)]
 struct AvraeSpell {
@@ -83,12 +100,17 @@ struct AvraeSpell {
 	school: SpellSchool,
 	classes: String,
 	// subclasses: String,
-	// #[serde(rename = "casttime")]
-	// cast_time: String,
-	// range: String,
-	// components: SpellComponents,
-	// duration: String,
+	#[serde(rename = "casttime", default)]
+	cast_time: String,
+	#[serde(default)]
+	range: String,
+	#[serde(default)]
+	components: SpellComponents,
+	#[serde(default)]
+	duration: String,
 	ritual: bool,
+	#[serde(default)]
+	concentration: bool,
 	description: String,
 }
 
@@ -106,6 +128,12 @@ impl From<AvraeSpell> for Spell {
 				.collect(),
 			description: value.description,
 			ritual: value.ritual,
+			concentration: value.concentration,
+			casting_time: value.cast_time,
+			range: value.range,
+			components: value.components.text(),
+			duration: value.duration,
+			source: SpellSource::default(),
 		}
 	}
 }
@@ -125,6 +153,7 @@ pub struct AvraeTome {
 
 impl From<AvraeTome> for SpellCollection {
 	fn from(value: AvraeTome) -> Self {
+		let srd = value.id == "srd";
 		Self {
 			id: Source::Avrae(value.id),
 			name: value.name,
@@ -133,8 +162,23 @@ impl From<AvraeTome> for SpellCollection {
 			} else {
 				Some(value.image)
 			},
-			spells: value.spells.into_iter().map(Into::into).collect(),
+			author: None,
+			version: None,
+			spells: value
+				.spells
+				.into_iter()
+				.map(|spell| {
+					let mut spell: Spell = spell.into();
+					// The SRD served by Avrae is SRD 5.1, from the 2014 rules.
+					if srd {
+						spell.source.edition = Some(Edition::Classic);
+					}
+					spell
+				})
+				.collect(),
 			spell_lists: HashMap::new(),
+			progressions: HashMap::new(),
+			classes: HashMap::new(),
 		}
 	}
 }
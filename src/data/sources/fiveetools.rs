@@ -1,12 +1,15 @@
 use std::{collections::HashMap, error::Error, sync::Arc};
 
 use anyhow::anyhow;
+use convert_case::{Case, Casing};
+use itertools::Itertools;
 use lazy_static::lazy_static;
+use regex::Regex;
 use reqwest::get;
 use serde::Deserialize;
 use tokio::sync::{Mutex, OnceCell};
 
-use crate::data::{Source, SpellCollection, SpellSchool};
+use crate::data::{Edition, Source, SpellCollection, SpellSchool, SpellSource};
 
 //const API_ENDPOINT: &str = "https://5e.tools/data"; // Protected by Cloudflare, ugh
 const API_ENDPOINT: &str = "https://5etools-mirror-1.github.io/data";
@@ -101,16 +104,145 @@ struct Meta {
 	ritual: bool,
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Time {
+	number: u32,
+	unit: String,
+}
+
+impl Time {
+	fn text(&self) -> String {
+		let unit = match self.unit.as_str() {
+			"bonus" => "bonus action",
+			unit => unit,
+		};
+		format!(
+			"{} {unit}{}",
+			self.number,
+			if self.number == 1 { "" } else { "s" }
+		)
+	}
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Distance {
+	#[serde(rename = "type")]
+	kind: String,
+	amount: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Range {
+	#[serde(rename = "type")]
+	kind: String,
+	distance: Distance,
+}
+
+impl Range {
+	fn text(&self) -> String {
+		let distance = &self.distance;
+		match (self.kind.as_str(), distance.amount) {
+			("special", _) => "Special".to_string(),
+			("point", Some(amount)) => format!("{amount} {}", distance.kind),
+			("point", None) => distance.kind.to_case(Case::Title),
+			(shape, Some(amount)) => format!(
+				"Self ({amount}-{} {shape})",
+				distance.kind.trim_end_matches('s').replace("feet", "foot")
+			),
+			(shape, None) => format!("Self ({shape})"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Components {
+	v: bool,
+	s: bool,
+	m: Option<serde_json::Value>,
+}
+
+impl Components {
+	fn text(&self) -> String {
+		let material = self.m.as_ref().map(|m| match m {
+			serde_json::Value::String(text) => format!("M ({text})"),
+			serde_json::Value::Object(obj) => obj
+				.get("text")
+				.and_then(serde_json::Value::as_str)
+				.map_or_else(|| "M".to_string(), |text| format!("M ({text})")),
+			_ => "M".to_string(),
+		});
+
+		[
+			self.v.then(|| "V".to_string()),
+			self.s.then(|| "S".to_string()),
+			material,
+		]
+		.into_iter()
+		.flatten()
+		.join(", ")
+	}
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct DurationAmount {
+	#[serde(rename = "type")]
+	kind: String,
+	amount: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Duration {
+	#[serde(rename = "type")]
+	kind: String,
+	#[serde(rename = "duration")]
+	length: DurationAmount,
+	concentration: bool,
+}
+
+impl Duration {
+	fn text(&self) -> String {
+		match self.kind.as_str() {
+			"instant" => "Instantaneous".to_string(),
+			"permanent" => "Until dispelled".to_string(),
+			"timed" => {
+				let DurationAmount { kind, amount } = &self.length;
+				let plural = if *amount == 1 { "" } else { "s" };
+				if self.concentration {
+					format!("Concentration, up to {amount} {kind}{plural}")
+				} else {
+					format!("{amount} {kind}{plural}")
+				}
+			}
+			kind => kind.to_case(Case::Title),
+		}
+	}
+}
+
+lazy_static! {
+	static ref TAG_REGEX: Regex = Regex::new(r"\{@\w+ ([^|}]*)[^}]*\}").unwrap();
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(default)]
 struct Spell {
 	name: String,
+	source: String,
+	page: Option<u32>,
 	level: u8,
 	// #[serde(deserialize_with = "deserialize_school")]
 	school: SpellSchool,
 	classes: Classes,
-	// #[serde()]
-	// entries: Vec<String>,
+	entries: Vec<serde_json::Value>,
+	time: Vec<Time>,
+	range: Range,
+	components: Components,
+	duration: Vec<Duration>,
 	meta: Meta,
 }
 
@@ -121,9 +253,23 @@ impl From<Spell> for crate::data::Spell {
 			level: value.level,
 			school: value.school,
 			classes: value.classes.into(),
-			description: String::new(),
-			// description: value.entries.join("\n"),
+			description: value
+				.entries
+				.iter()
+				.filter_map(serde_json::Value::as_str)
+				.map(|entry| TAG_REGEX.replace_all(entry, "$1"))
+				.join("\n\n"),
 			ritual: value.meta.ritual,
+			concentration: value.duration.iter().any(|d| d.concentration),
+			casting_time: value.time.iter().map(Time::text).join(" or "),
+			range: value.range.text(),
+			components: value.components.text(),
+			duration: value.duration.iter().map(Duration::text).join(" or "),
+			source: SpellSource {
+				book: value.source,
+				page: value.page,
+				..Default::default()
+			},
 		}
 	}
 }
@@ -152,14 +298,144 @@ impl Book {
 	}
 }
 
+/// Books of the 2024 revision of the rules, every other published book is from 2014.
+const EDITION_2024_BOOKS: [&str; 3] = ["xphb", "xdmg", "xmm"];
+
 impl From<Book> for SpellCollection {
 	fn from(value: Book) -> Self {
+		let edition = if EDITION_2024_BOOKS.contains(&value.id.as_str()) {
+			Edition::Modern
+		} else {
+			Edition::Classic
+		};
+
 		Self {
 			id: Source::FiveE(value.id.clone()),
 			name: value.id,
 			image: None,
+			author: None,
+			version: None,
+			spells: value
+				.spells
+				.into_iter()
+				.map(|spell| {
+					let mut spell: crate::data::Spell = spell.into();
+					spell.source.edition = Some(edition);
+					spell
+				})
+				.collect(),
+			spell_lists: HashMap::new(),
+			progressions: HashMap::new(),
+			classes: HashMap::new(),
+		}
+	}
+}
+
+/// A source declared in a homebrew file's `_meta`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct HomebrewSource {
+	json: String,
+	full: String,
+	authors: Vec<String>,
+	version: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct HomebrewMeta {
+	sources: Vec<HomebrewSource>,
+}
+
+/// Only checks for the `_meta` section, so other tome formats aren't parsed twice over.
+#[derive(Deserialize)]
+struct HomebrewProbe {
+	#[serde(rename = "_meta")]
+	meta: Option<serde::de::IgnoredAny>,
+}
+
+/// A file in the 5etools homebrew format, where spells list their classes themselves.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Homebrew {
+	#[serde(skip)]
+	url: String,
+	#[serde(rename = "_meta")]
+	meta: HomebrewMeta,
+	#[serde(rename = "spell")]
+	spells: Vec<Spell>,
+}
+
+pub fn is_homebrew(content: &[u8]) -> bool {
+	serde_json::from_slice::<HomebrewProbe>(content).is_ok_and(|probe| probe.meta.is_some())
+}
+
+pub fn parse_homebrew(url: &str, content: &[u8]) -> anyhow::Result<Homebrew> {
+	let de = &mut serde_json::Deserializer::from_slice(content);
+	let mut homebrew: Homebrew =
+		serde_path_to_error::deserialize(de).map_err(super::json::located)?;
+
+	if homebrew.spells.is_empty() {
+		return Err(anyhow!("{url} has no spells"));
+	}
+	homebrew.url = url.to_string();
+	Ok(homebrew)
+}
+
+impl Homebrew {
+	/// Full names of the sources the spells come from, in the order `_meta` declares them.
+	fn source_names(&self) -> Vec<&str> {
+		self.meta
+			.sources
+			.iter()
+			.filter(|source| self.spells.iter().any(|spell| spell.source == source.json))
+			.map(|source| {
+				if source.full.is_empty() {
+					source.json.as_str()
+				} else {
+					source.full.as_str()
+				}
+			})
+			.collect()
+	}
+}
+
+impl From<Homebrew> for SpellCollection {
+	fn from(value: Homebrew) -> Self {
+		let names = value.source_names();
+		let name = if names.is_empty() {
+			value
+				.url
+				.split('/')
+				.next_back()
+				.unwrap_or(&value.url)
+				.to_string()
+		} else {
+			names.join(", ")
+		};
+		let author = value
+			.meta
+			.sources
+			.iter()
+			.flat_map(|source| &source.authors)
+			.unique()
+			.join(", ");
+		let version = value
+			.meta
+			.sources
+			.iter()
+			.find_map(|source| source.version.clone());
+
+		Self {
+			id: Source::FiveEHomebrew(value.url),
+			name,
+			image: None,
+			author: (!author.is_empty()).then_some(author),
+			version,
 			spells: value.spells.into_iter().map(Into::into).collect(),
 			spell_lists: HashMap::new(),
+			progressions: HashMap::new(),
+			classes: HashMap::new(),
 		}
 	}
 }
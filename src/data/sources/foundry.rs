@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::data::{Source, Spell, SpellCollection, SpellSchool, SpellSource};
+
+/// Guild tome sources starting with this are Foundry VTT dnd5e compendium exports:
+///
+/// `foundry:<url> [classes=<field>] [lists=<url>]`
+///
+/// `classes` is a dotted path to a field holding each spell's classes, e.g.
+/// `flags.spell-class-filter.classes`. `lists` is a tome whose `spell_lists` assign the spells to
+/// classes instead.
+pub const PREFIX: &str = "foundry:";
+
+lazy_static! {
+	static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+	static ref ENRICHER: Regex = Regex::new(r"@\w+\[[^\]]*\](?:\{([^}]*)\})?").unwrap();
+	static ref INLINE_ROLL: Regex = Regex::new(r"\[\[/r(?:oll)? ([^\]]*)\]\]").unwrap();
+}
+
+#[derive(Debug, Default)]
+struct Options {
+	url: String,
+	class_field: Option<String>,
+	lists: Option<String>,
+}
+
+impl Options {
+	fn parse(source: &str) -> anyhow::Result<Self> {
+		let mut parts = source.split_whitespace();
+		let mut options = Self {
+			url: parts
+				.next()
+				.ok_or_else(|| anyhow!("{PREFIX} needs a compendium URL"))?
+				.to_string(),
+			..Default::default()
+		};
+
+		for part in parts {
+			match part.split_once('=') {
+				Some(("classes", field)) => options.class_field = Some(field.to_string()),
+				Some(("lists", url)) => options.lists = Some(url.to_string()),
+				_ => return Err(anyhow!("Unknown {PREFIX} option: {part}")),
+			}
+		}
+		Ok(options)
+	}
+}
+
+pub async fn get_compendium(source: &str) -> anyhow::Result<Compendium> {
+	log::info!("Grabbing: {PREFIX}{source}");
+	let options = Options::parse(source)?;
+
+	let resp = reqwest::get(Url::parse(&options.url)?)
+		.await?
+		.error_for_status()?;
+	let documents = parse_documents(&resp.text().await?)?;
+
+	let spell_lists = match &options.lists {
+		Some(url) => {
+			let (content, format) = super::json::fetch(Url::parse(url)?).await?;
+			super::parse_tome(url, &content, format)?.spell_lists
+		}
+		None => HashMap::new(),
+	};
+
+	let spells: Vec<Spell> = documents
+		.iter()
+		.filter(|doc| doc["type"].as_str() == Some("spell"))
+		.map(|doc| to_spell(doc, options.class_field.as_deref()))
+		.collect();
+	if spells.is_empty() {
+		return Err(anyhow!("No spells found in {}", options.url));
+	}
+
+	Ok(Compendium {
+		source: source.to_string(),
+		url: options.url,
+		spells,
+		spell_lists,
+	})
+}
+
+/// Reads a JSON export (a single document, an array, or an object with `items`) or a `NeDB`
+/// file (one JSON document per line).
+fn parse_documents(text: &str) -> anyhow::Result<Vec<Value>> {
+	if let Ok(value) = serde_json::from_str::<Value>(text) {
+		return Ok(match value {
+			Value::Array(documents) => documents,
+			Value::Object(ref object) => match object.get("items") {
+				Some(Value::Array(documents)) => documents.clone(),
+				_ => vec![value],
+			},
+			_ => Vec::new(),
+		});
+	}
+
+	text.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty())
+		.enumerate()
+		.map(|(i, line)| serde_json::from_str(line).map_err(|err| anyhow!("line {}: {err}", i + 1)))
+		.collect()
+}
+
+/// Formats a number or string field, Foundry stores both.
+fn text(value: &Value) -> String {
+	match value {
+		Value::String(text) => text.trim().to_string(),
+		Value::Number(number) => number.to_string(),
+		_ => String::new(),
+	}
+}
+
+fn plural(count: &str, unit: &str) -> String {
+	if count == "1" || count.is_empty() {
+		format!("1 {unit}")
+	} else {
+		format!("{count} {unit}s")
+	}
+}
+
+fn school(code: &str) -> SpellSchool {
+	match code {
+		"abj" => SpellSchool::Abjuration,
+		"con" => SpellSchool::Conjuration,
+		"div" => SpellSchool::Divination,
+		"enc" => SpellSchool::Enchantment,
+		"evo" => SpellSchool::Evocation,
+		"ill" => SpellSchool::Illusion,
+		"nec" => SpellSchool::Necromancy,
+		"trs" => SpellSchool::Transmutation,
+		other => SpellSchool::from(other.to_string()),
+	}
+}
+
+fn casting_time(system: &Value) -> String {
+	let activation = &system["activation"];
+	let cost = text(&activation["cost"]);
+	match activation["type"].as_str().unwrap_or_default() {
+		"action" => plural(&cost, "action"),
+		"bonus" => plural(&cost, "bonus action"),
+		"reaction" => plural(&cost, "reaction"),
+		"minute" | "hour" | "day" => plural(&cost, activation["type"].as_str().unwrap_or_default()),
+		"special" => "Special".to_string(),
+		other => other.to_string(),
+	}
+}
+
+fn range(system: &Value) -> String {
+	let range = &system["range"];
+	let value = text(&range["value"]);
+	match range["units"].as_str().unwrap_or_default() {
+		"self" => "Self".to_string(),
+		"touch" => "Touch".to_string(),
+		"ft" => format!("{value} feet"),
+		"mi" => plural(&value, "mile"),
+		"any" => "Unlimited".to_string(),
+		"spec" => "Special".to_string(),
+		other => format!("{value} {other}").trim().to_string(),
+	}
+}
+
+fn duration(system: &Value) -> String {
+	let duration = &system["duration"];
+	let value = text(&duration["value"]);
+	match duration["units"].as_str().unwrap_or_default() {
+		"inst" => "Instantaneous".to_string(),
+		"perm" => "Until dispelled".to_string(),
+		"spec" => "Special".to_string(),
+		"turn" | "round" | "minute" | "hour" | "day" | "month" | "year" => {
+			plural(&value, duration["units"].as_str().unwrap_or_default())
+		}
+		other => other.to_string(),
+	}
+}
+
+/// Spell properties: `system.properties` in dnd5e 3.x, `system.components` before that.
+fn properties(system: &Value) -> Vec<String> {
+	match &system["properties"] {
+		Value::Array(properties) => properties
+			.iter()
+			.filter_map(Value::as_str)
+			.map(String::from)
+			.collect(),
+		_ => system["components"]
+			.as_object()
+			.map(|components| {
+				components
+					.iter()
+					.filter(|(_, value)| value.as_bool().unwrap_or_default())
+					.map(|(key, _)| key.clone())
+					.collect()
+			})
+			.unwrap_or_default(),
+	}
+}
+
+fn components(system: &Value, properties: &[String]) -> String {
+	let components = [("vocal", "V"), ("somatic", "S"), ("material", "M")]
+		.iter()
+		.filter(|(property, _)| properties.iter().any(|p| p == property))
+		.map(|(_, code)| *code)
+		.join(", ");
+
+	let materials = text(&system["materials"]["value"]);
+	if materials.is_empty() {
+		components
+	} else {
+		format!("{components} ({materials})")
+	}
+}
+
+fn description(html: &str) -> String {
+	let text = html
+		.replace("</p>", "\n\n")
+		.replace("<br>", "\n")
+		.replace("<br />", "\n");
+	let text = HTML_TAG.replace_all(&text, "");
+	let text = ENRICHER.replace_all(&text, "$1");
+	let text = INLINE_ROLL.replace_all(&text, "$1");
+
+	text.replace("&nbsp;", " ")
+		.replace("&amp;", "&")
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.trim()
+		.to_string()
+}
+
+/// Looks up a dotted path like `flags.module.classes`, holding a list or a comma separated string.
+fn classes(doc: &Value, field: &str) -> Vec<String> {
+	let value = field.split('.').fold(doc, |value, key| &value[key]);
+	match value {
+		Value::Array(classes) => classes.iter().map(text).collect(),
+		Value::String(classes) => classes.split(',').map(|c| c.trim().to_string()).collect(),
+		_ => Vec::new(),
+	}
+	.into_iter()
+	.filter(|class| !class.is_empty())
+	.collect()
+}
+
+/// `system.source` is a plain string before dnd5e 3.x, e.g. `PHB pg. 241`.
+fn source(system: &Value) -> SpellSource {
+	let (book, page) = match &system["source"] {
+		Value::Object(source) => (
+			source.get("book").map(text).unwrap_or_default(),
+			source.get("page").map(text).unwrap_or_default(),
+		),
+		Value::String(source) => match source.split_once(" pg.") {
+			Some((book, page)) => (book.trim().to_string(), page.trim().to_string()),
+			None => (source.trim().to_string(), String::new()),
+		},
+		_ => (String::new(), String::new()),
+	};
+
+	SpellSource {
+		book,
+		page: page.parse().ok(),
+		..Default::default()
+	}
+}
+
+fn to_spell(doc: &Value, class_field: Option<&str>) -> Spell {
+	// dnd5e moved item data from `data` to `system` in Foundry v10.
+	let system = doc.get("system").unwrap_or(&doc["data"]);
+	let properties = properties(system);
+	let has = |property: &str| properties.iter().any(|p| p == property);
+
+	Spell {
+		name: text(&doc["name"]),
+		level: text(&system["level"]).parse().unwrap_or_default(),
+		school: school(system["school"].as_str().unwrap_or_default()),
+		classes: class_field
+			.map(|field| classes(doc, field))
+			.unwrap_or_default(),
+		description: description(system["description"]["value"].as_str().unwrap_or_default()),
+		ritual: has("ritual"),
+		concentration: has("concentration"),
+		casting_time: casting_time(system),
+		range: range(system),
+		components: components(system, &properties),
+		duration: duration(system),
+		source: source(system),
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Compendium {
+	source: String,
+	url: String,
+	spells: Vec<Spell>,
+	spell_lists: HashMap<String, Vec<String>>,
+}
+
+impl From<Compendium> for SpellCollection {
+	fn from(value: Compendium) -> Self {
+		Self {
+			name: value
+				.url
+				.split('/')
+				.next_back()
+				.unwrap_or(&value.url)
+				.to_string(),
+			id: Source::Foundry(value.source),
+			image: None,
+			author: None,
+			version: None,
+			spells: value.spells,
+			spell_lists: value.spell_lists,
+			progressions: HashMap::new(),
+			classes: HashMap::new(),
+		}
+	}
+}
@@ -1,41 +1,164 @@
 use std::collections::HashMap;
 
+use anyhow::anyhow;
 use reqwest::Url;
 use serde::Deserialize;
 
-use crate::data::{Source, SpellCollection};
+use crate::data::{progression::Progression, ClassInfo, Source, Spell, SpellCollection};
 
-pub async fn get_tome(url: Url) -> anyhow::Result<Tome> {
-	let str = url.to_string();
-	let resp = reqwest::get(url).await?;
+/// Fetches a tome file, detecting its format from the content type or file extension.
+pub async fn fetch(url: Url) -> anyhow::Result<(Vec<u8>, Format)> {
+	let path = url.path().to_string();
+	let resp = reqwest::get(url).await?.error_for_status()?;
 
-	let mut tome: Tome = serde_json::from_slice(&resp.bytes().await?)?;
-	tome.url = str;
+	let content_type = resp
+		.headers()
+		.get(reqwest::header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+		.map(String::from);
+	let format = Format::detect(content_type.as_deref(), &path);
+
+	Ok((resp.bytes().await?.to_vec(), format))
+}
+
+/// File formats a tome can be written in. They all describe the same [`Tome`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+	#[default]
+	Json,
+	Yaml,
+	Toml,
+}
+
+impl Format {
+	/// Detects the format from a content type or file name, falling back to JSON.
+	pub fn detect(content_type: Option<&str>, name: &str) -> Self {
+		let content_type = content_type.unwrap_or_default().to_lowercase();
+		let extension = std::path::Path::new(name)
+			.extension()
+			.and_then(|ext| ext.to_str())
+			.unwrap_or_default()
+			.to_lowercase();
+
+		if content_type.contains("yaml") || matches!(extension.as_str(), "yaml" | "yml") {
+			Self::Yaml
+		} else if content_type.contains("toml") || extension == "toml" {
+			Self::Toml
+		} else {
+			Self::Json
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Json => "json",
+			Self::Yaml => "yaml",
+			Self::Toml => "toml",
+		}
+	}
+}
+
+impl From<&str> for Format {
+	fn from(value: &str) -> Self {
+		match value {
+			"yaml" => Self::Yaml,
+			"toml" => Self::Toml,
+			_ => Self::Json,
+		}
+	}
+}
+
+/// Names the offending value in a parse error, the underlying error adds line and column.
+pub(super) fn located<E: std::fmt::Display>(err: serde_path_to_error::Error<E>) -> anyhow::Error {
+	let path = err.path().to_string();
+	if path == "." {
+		anyhow!("{}", err.into_inner())
+	} else {
+		anyhow!("{path}: {}", err.into_inner())
+	}
+}
+
+/// Parses a tome that was fetched or uploaded. `url` names the tome in the spell map.
+///
+/// Errors point at the offending value, e.g.
+/// `spells[3].level: invalid type: string "3", expected u8 at line 40 column 15`.
+pub fn parse_tome(url: &str, content: &[u8], format: Format) -> anyhow::Result<Tome> {
+	let mut tome: Tome = match format {
+		Format::Json => {
+			serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(content))
+				.map_err(located)?
+		}
+		Format::Yaml => {
+			serde_path_to_error::deserialize(serde_yaml::Deserializer::from_slice(content))
+				.map_err(located)?
+		}
+		Format::Toml => {
+			serde_path_to_error::deserialize(toml::Deserializer::new(std::str::from_utf8(content)?))
+				.map_err(located)?
+		}
+	};
+
+	if tome.spells.is_empty() && tome.spell_lists.is_empty() && tome.classes.is_empty() {
+		return Err(anyhow!("{url} has no spells, spell lists or classes"));
+	}
+	tome.url = url.to_string();
 	Ok(tome)
 }
 
+/// A tome hosted as JSON, YAML or TOML, see `tome.schema.json`. Every field is optional, so
+/// older tomes with only `spell_lists` still load.
 #[derive(Debug, Default, Deserialize)]
 #[serde(default)]
 pub struct Tome {
+	#[serde(skip)]
 	url: String,
 
+	name: Option<String>,
+	author: Option<String>,
+	image: Option<String>,
+	version: Option<String>,
+
+	spells: Vec<Spell>,
 	spell_lists: HashMap<String, Vec<String>>,
+	classes: HashMap<String, TomeClass>,
+}
+
+/// A class entry: its spellcasting progression and descriptive metadata side by side.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TomeClass {
+	#[serde(flatten)]
+	progression: Progression,
+	#[serde(flatten)]
+	info: ClassInfo,
 }
 
 impl From<Tome> for SpellCollection {
 	fn from(value: Tome) -> Self {
-		let name = value
-			.url
-			.split('/')
-			.last()
-			.unwrap_or(&value.url)
-			.to_string();
+		let name = value.name.unwrap_or_else(|| {
+			value
+				.url
+				.split('/')
+				.last()
+				.unwrap_or(&value.url)
+				.to_string()
+		});
+		let (progressions, classes) = value
+			.classes
+			.into_iter()
+			.map(|(class, entry)| ((class.clone(), entry.progression), (class, entry.info)))
+			.unzip();
+
 		Self {
 			id: Source::Json(value.url),
 			name,
-			image: None,
-			spells: Vec::new(),
+			image: value.image,
+			author: value.author,
+			version: value.version,
+			spells: value.spells,
 			spell_lists: value.spell_lists,
+			progressions,
+			classes,
 		}
 	}
 }
@@ -5,9 +5,14 @@ use super::SpellCollection;
 
 mod avrae;
 mod fiveetools;
+mod foundry;
 mod json;
+mod open5e;
 
 pub use fiveetools::get_index as get_5e_index;
+pub use foundry::PREFIX as FOUNDRY_PREFIX;
+pub use json::Format as TomeFormat;
+pub use open5e::PREFIX as OPEN5E_PREFIX;
 
 lazy_static! {
 	static ref REGEX: Regex = Regex::new(r".*\d.*").unwrap();
@@ -15,11 +20,30 @@ lazy_static! {
 
 pub async fn get_spells(source: &str) -> anyhow::Result<SpellCollection> {
 	// log::info!("Get spells from source: {source}");
-	if let Ok(url) = reqwest::Url::parse(source) {
-		json::get_tome(url).await.map(Into::into)
+	if let Some(slug) = source.strip_prefix(open5e::PREFIX) {
+		open5e::get_document(slug).await.map(Into::into)
+	} else if let Some(source) = source.strip_prefix(foundry::PREFIX) {
+		foundry::get_compendium(source).await.map(Into::into)
+	} else if let Ok(url) = reqwest::Url::parse(source) {
+		let (content, format) = json::fetch(url).await?;
+		parse_tome(source, &content, format)
 	} else if REGEX.is_match(source) || source.eq("srd") {
 		avrae::get_tome(source).await.map(Into::into)
 	} else {
 		fiveetools::get_source(source).await.map(Into::into)
 	}
 }
+
+/// Parses a tome file, either fetched from a URL or uploaded. 5etools homebrew files are
+/// recognised by their `_meta` section, anything else is read as a [`json::Tome`].
+pub fn parse_tome(
+	name: &str,
+	content: &[u8],
+	format: TomeFormat,
+) -> anyhow::Result<SpellCollection> {
+	if format == TomeFormat::Json && fiveetools::is_homebrew(content) {
+		fiveetools::parse_homebrew(name, content).map(Into::into)
+	} else {
+		json::parse_tome(name, content, format).map(Into::into)
+	}
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, env};
+
+use anyhow::anyhow;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::data::{Source, Spell, SpellCollection, SpellSchool, SpellSource};
+
+const DEFAULT_API_ENDPOINT: &str = "https://api.open5e.com";
+
+/// Guild tome sources starting with this are read from the Open5e API. The rest of the source
+/// is a document slug, e.g. `open5e:dmag` for Deep Magic.
+pub const PREFIX: &str = "open5e:";
+
+/// Set `OPEN5E_API` to point at a local mirror.
+fn api_endpoint() -> String {
+	env::var("OPEN5E_API").map_or_else(
+		|_| DEFAULT_API_ENDPOINT.to_string(),
+		|url| url.trim_end_matches('/').to_string(),
+	)
+}
+
+pub async fn get_document(slug: &str) -> anyhow::Result<Document> {
+	log::info!("Grabbing: {PREFIX}{slug}");
+
+	let mut url = Some(Url::parse_with_params(
+		&format!("{}/v1/spells/", api_endpoint()),
+		&[("document__slug", slug), ("limit", "500")],
+	)?);
+
+	let mut document = Document {
+		slug: slug.to_string(),
+		..Default::default()
+	};
+	while let Some(page_url) = url {
+		let resp = reqwest::get(page_url).await?.error_for_status()?;
+		let page: Page = serde_json::from_slice(&resp.bytes().await?)
+			.map_err(|err| anyhow!("Deserialization error for {PREFIX}{slug}: {err}"))?;
+
+		document.spells.extend(page.results);
+		url = page.next.as_deref().map(Url::parse).transpose()?;
+	}
+
+	if document.spells.is_empty() {
+		return Err(anyhow!("No spells found for {PREFIX}{slug}"));
+	}
+	document.title = document
+		.spells
+		.first()
+		.map(|spell| spell.document_title.clone())
+		.unwrap_or_default();
+
+	Ok(document)
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+	next: Option<String>,
+	results: Vec<Open5eSpell>,
+}
+
+/// Older API versions send `"yes"`/`"no"`, newer ones booleans.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Flag {
+	Bool(bool),
+	Text(String),
+}
+
+impl Default for Flag {
+	fn default() -> Self {
+		Self::Bool(false)
+	}
+}
+
+impl From<Flag> for bool {
+	fn from(value: Flag) -> Self {
+		match value {
+			Flag::Bool(value) => value,
+			Flag::Text(text) => text.eq_ignore_ascii_case("yes"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Open5eSpell {
+	name: String,
+	desc: String,
+	higher_level: String,
+	range: String,
+	components: String,
+	material: String,
+	ritual: Flag,
+	duration: String,
+	concentration: Flag,
+	casting_time: String,
+	level_int: u8,
+	school: String,
+	dnd_class: String,
+	#[serde(rename = "document__title")]
+	document_title: String,
+	#[serde(rename = "document__slug")]
+	document_slug: String,
+}
+
+impl From<Open5eSpell> for Spell {
+	fn from(value: Open5eSpell) -> Self {
+		let mut description = value.desc;
+		if !value.higher_level.is_empty() {
+			description += "\n\n**At Higher Levels.** ";
+			description += &value.higher_level;
+		}
+
+		let components = if value.material.is_empty() {
+			value.components
+		} else {
+			format!("{} ({})", value.components, value.material)
+		};
+
+		Self {
+			name: value.name,
+			level: value.level_int,
+			school: SpellSchool::from(value.school),
+			classes: value
+				.dnd_class
+				.split(',')
+				.map(str::trim)
+				.filter(|class| !class.is_empty())
+				.map(Into::into)
+				.collect(),
+			description,
+			ritual: value.ritual.into(),
+			concentration: value.concentration.into(),
+			casting_time: value.casting_time,
+			range: value.range,
+			components,
+			duration: value.duration,
+			source: SpellSource {
+				book: value.document_slug,
+				..Default::default()
+			},
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+	slug: String,
+	title: String,
+	spells: Vec<Open5eSpell>,
+}
+
+impl From<Document> for SpellCollection {
+	fn from(value: Document) -> Self {
+		Self {
+			name: if value.title.is_empty() {
+				value.slug.clone()
+			} else {
+				value.title
+			},
+			id: Source::Open5e(value.slug),
+			image: None,
+			author: None,
+			version: None,
+			spells: value.spells.into_iter().map(Into::into).collect(),
+			spell_lists: HashMap::new(),
+			progressions: HashMap::new(),
+			classes: HashMap::new(),
+		}
+	}
+}
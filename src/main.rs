@@ -11,8 +11,9 @@ use dotenvy::dotenv;
 use poise::serenity_prelude::{self as serenity, CacheHttp, GuildId};
 use tokio::sync::{Mutex, RwLock};
 
-use commands::build_spell_map;
 use commands::spells::SpellMap;
+use commands::{build_spell_map, load_all_settings};
+use models::GuildSetting;
 
 mod commands;
 mod data;
@@ -23,9 +24,13 @@ mod schema;
 pub struct Data {
 	db: Arc<Mutex<MysqlConnection>>,
 	spell_map: Arc<RwLock<HashMap<GuildId, SpellMap>>>,
+	guild_settings: Arc<RwLock<HashMap<GuildId, GuildSetting>>>,
+	/// Prefix for guilds that didn't pick their own.
+	prefix: String,
 }
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
+type ApplicationContext<'a> = poise::ApplicationContext<'a, Data, Error>;
 
 impl Debug for Data {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -49,8 +54,11 @@ async fn main() {
 	let framework = poise::Framework::builder()
 		.options(poise::FrameworkOptions {
 			commands: commands::commands(),
+			event_handler: |ctx, event, framework, data| {
+				Box::pin(commands::event_handler(ctx, event, framework, data))
+			},
 			prefix_options: poise::PrefixFrameworkOptions {
-				prefix: Some(env::var("PREFIX").unwrap_or_else(|_| "!!".to_string())),
+				dynamic_prefix: Some(commands::dynamic_prefix),
 				..Default::default()
 			},
 			..Default::default()
@@ -76,9 +84,13 @@ async fn main() {
 				}
 				log::info!("Done");
 
+				let guild_settings = load_all_settings(&mut *connection.lock().await);
+
 				Ok(Data {
 					db: connection.clone(),
 					spell_map: Arc::new(RwLock::new(spell_map)),
+					guild_settings: Arc::new(RwLock::new(guild_settings)),
+					prefix: env::var("PREFIX").unwrap_or_else(|_| "!!".to_string()),
 				})
 			})
 		});
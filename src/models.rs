@@ -6,6 +6,8 @@ pub struct GuildTome {
 	pub id: u32,
 	pub guild: u64,
 	pub source: String,
+	pub priority: i32,
+	pub policy: String,
 }
 
 #[derive(Insertable)]
@@ -13,4 +15,188 @@ pub struct GuildTome {
 pub struct NewGuildTome<'a> {
 	pub guild: u64,
 	pub source: &'a str,
+	pub priority: i32,
+	pub policy: &'a str,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = GuildSettings)]
+pub struct GuildSetting {
+	pub guild: u64,
+	pub edition: String,
+	pub public_lists: bool,
+	pub shared_pages: bool,
+	pub prefix: Option<String>,
+	pub manager_role: Option<u64>,
+	pub announcement_channel: Option<u64>,
+	pub include_ua: bool,
+}
+
+#[derive(Debug, Queryable)]
+pub struct Spellbook {
+	pub id: u32,
+	pub name: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = Spellbooks)]
+pub struct NewSpellbook<'a> {
+	pub guild: u64,
+	pub owner: u64,
+	pub name: &'a str,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = SpellbookSpells)]
+pub struct NewSpellbookSpell<'a> {
+	pub spellbook: u32,
+	pub spell: &'a str,
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, AsChangeset)]
+#[diesel(table_name = SlotTrackers, treat_none_as_null = true)]
+pub struct SlotTracker {
+	pub id: u32,
+	pub guild: u64,
+	pub owner: u64,
+	pub name: String,
+	pub class: String,
+	pub level: u8,
+	pub caster: Option<String>,
+	pub spent: String,
+	pub pact_spent: u8,
+	pub arcane_recovery: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = SlotTrackers)]
+pub struct NewSlotTracker<'a> {
+	pub guild: u64,
+	pub owner: u64,
+	pub name: &'a str,
+	pub class: &'a str,
+	pub level: u8,
+	pub caster: Option<String>,
+}
+
+#[derive(Debug, Queryable)]
+pub struct Concentration {
+	pub id: u32,
+	pub owner: u64,
+	pub name: String,
+	pub spell: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = Concentrations)]
+pub struct NewConcentration<'a> {
+	pub guild: u64,
+	pub channel: u64,
+	pub owner: u64,
+	pub name: &'a str,
+	pub spell: &'a str,
+}
+
+#[derive(Debug, Queryable)]
+pub struct TomeSnapshot {
+	pub source: String,
+	pub content: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = TomeSnapshots)]
+pub struct NewTomeSnapshot<'a> {
+	pub guild: u64,
+	pub source: &'a str,
+	pub hash: u64,
+	pub created: u64,
+	pub content: &'a str,
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, AsChangeset)]
+#[diesel(table_name = HomebrewSpells)]
+pub struct HomebrewSpell {
+	pub id: u32,
+	pub guild: u64,
+	pub name: String,
+	pub level: u8,
+	pub school: String,
+	pub classes: String,
+	pub ritual: bool,
+	pub concentration: bool,
+	pub casting_time: String,
+	pub range: String,
+	pub components: String,
+	pub duration: String,
+	pub description: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = HomebrewSpells)]
+pub struct NewHomebrewSpell<'a> {
+	pub guild: u64,
+	pub name: &'a str,
+	pub level: u8,
+	pub school: &'a str,
+	pub classes: &'a str,
+	pub ritual: bool,
+	pub concentration: bool,
+	pub casting_time: &'a str,
+	pub range: &'a str,
+	pub components: &'a str,
+	pub duration: &'a str,
+	pub description: &'a str,
+}
+
+#[derive(Debug, Queryable)]
+pub struct HomebrewSpellEdit {
+	pub editor: u64,
+	pub created: u64,
+	pub content: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = HomebrewSpellEdits)]
+pub struct NewHomebrewSpellEdit<'a> {
+	pub spell: u32,
+	pub editor: u64,
+	pub created: u64,
+	pub content: &'a str,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = UploadedTomes)]
+pub struct NewUploadedTome<'a> {
+	pub guild: u64,
+	pub name: &'a str,
+	pub format: &'a str,
+	pub content: &'a str,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = UserSettings)]
+pub struct UserSetting {
+	pub user: u64,
+	pub page_size: u8,
+}
+
+#[derive(Debug, Queryable)]
+pub struct PaginatedMessage {
+	pub id: u64,
+	pub page_size: u8,
+	pub content: String,
+	pub owner: Option<u64>,
+	pub title: Option<String>,
+	pub spell_filter: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = PaginatedMessages)]
+pub struct NewPaginatedMessage<'a> {
+	pub id: u64,
+	pub page_size: u8,
+	pub content: &'a str,
+	pub owner: Option<u64>,
+	pub title: Option<&'a str>,
+	pub spell_filter: Option<&'a str>,
 }
@@ -1,10 +1,153 @@
 #![allow(non_snake_case)]
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    Concentrations (id) {
+        id -> Unsigned<Integer>,
+        guild -> Unsigned<Bigint>,
+        channel -> Unsigned<Bigint>,
+        owner -> Unsigned<Bigint>,
+        name -> Varchar,
+        spell -> Varchar,
+    }
+}
+
+diesel::table! {
+    GuildSettings (guild) {
+        guild -> Unsigned<Bigint>,
+        edition -> Varchar,
+        public_lists -> Bool,
+        shared_pages -> Bool,
+        prefix -> Nullable<Varchar>,
+        manager_role -> Nullable<Unsigned<Bigint>>,
+        announcement_channel -> Nullable<Unsigned<Bigint>>,
+        include_ua -> Bool,
+    }
+}
+
 diesel::table! {
     GuildTomes (id) {
         id -> Unsigned<Integer>,
         guild -> Unsigned<Bigint>,
         source -> Text,
+        priority -> Integer,
+        policy -> Varchar,
+    }
+}
+
+diesel::table! {
+    HomebrewSpellEdits (id) {
+        id -> Unsigned<Integer>,
+        spell -> Unsigned<Integer>,
+        editor -> Unsigned<Bigint>,
+        created -> Unsigned<Bigint>,
+        content -> Text,
+    }
+}
+
+diesel::table! {
+    HomebrewSpells (id) {
+        id -> Unsigned<Integer>,
+        guild -> Unsigned<Bigint>,
+        name -> Varchar,
+        level -> Unsigned<Tinyint>,
+        school -> Varchar,
+        classes -> Varchar,
+        ritual -> Bool,
+        concentration -> Bool,
+        casting_time -> Varchar,
+        range -> Varchar,
+        components -> Varchar,
+        duration -> Varchar,
+        description -> Text,
+    }
+}
+
+diesel::table! {
+    PaginatedMessages (id) {
+        id -> Unsigned<Bigint>,
+        page_size -> Unsigned<Tinyint>,
+        content -> Longtext,
+        owner -> Nullable<Unsigned<Bigint>>,
+        title -> Nullable<Varchar>,
+        spell_filter -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    SlotTrackers (id) {
+        id -> Unsigned<Integer>,
+        guild -> Unsigned<Bigint>,
+        owner -> Unsigned<Bigint>,
+        name -> Varchar,
+        class -> Varchar,
+        level -> Unsigned<Tinyint>,
+        caster -> Nullable<Varchar>,
+        spent -> Varchar,
+        pact_spent -> Unsigned<Tinyint>,
+        arcane_recovery -> Bool,
+    }
+}
+
+diesel::table! {
+    SpellbookSpells (id) {
+        id -> Unsigned<Integer>,
+        spellbook -> Unsigned<Integer>,
+        spell -> Varchar,
+    }
+}
+
+diesel::table! {
+    Spellbooks (id) {
+        id -> Unsigned<Integer>,
+        guild -> Unsigned<Bigint>,
+        owner -> Unsigned<Bigint>,
+        name -> Varchar,
+    }
+}
+
+diesel::table! {
+    TomeSnapshots (id) {
+        id -> Unsigned<Integer>,
+        guild -> Unsigned<Bigint>,
+        source -> Text,
+        hash -> Unsigned<Bigint>,
+        created -> Unsigned<Bigint>,
+        content -> Longtext,
     }
 }
+
+diesel::table! {
+    UploadedTomes (id) {
+        id -> Unsigned<Integer>,
+        guild -> Unsigned<Bigint>,
+        name -> Varchar,
+        format -> Varchar,
+        content -> Longtext,
+    }
+}
+
+diesel::table! {
+    UserSettings (user) {
+        user -> Unsigned<Bigint>,
+        page_size -> Unsigned<Tinyint>,
+    }
+}
+
+diesel::joinable!(HomebrewSpellEdits -> HomebrewSpells (spell));
+diesel::joinable!(SpellbookSpells -> Spellbooks (spellbook));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    Concentrations,
+    GuildSettings,
+    GuildTomes,
+    HomebrewSpellEdits,
+    HomebrewSpells,
+    PaginatedMessages,
+    SlotTrackers,
+    SpellbookSpells,
+    Spellbooks,
+    TomeSnapshots,
+    UploadedTomes,
+    UserSettings,
+);